@@ -17,9 +17,15 @@
  *   <https://www.gnu.org/licenses/>. */
 
 use crate::dsp;
+use crate::headless::{self, FftSink};
+use crate::recorder::Recorder;
+use crate::rtl_tcp_server::RtlTcpServer;
+use crate::source::{IqSink, IqSource};
 use crate::ui;
+use std::io;
+use std::net::ToSocketAddrs;
 use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
-use std::sync::mpsc::sync_channel;
+use std::sync::mpsc::{sync_channel, Receiver};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -27,106 +33,181 @@ use std::time::Duration;
 pub struct WaterfallDemo {
     center_frequency: Arc<AtomicU32>,
     control_thread: Option<thread::JoinHandle<()>>,
-    fft_window: dsp::WindowType,
+    fft_sinks: Vec<Box<dyn FftSink>>,
     gain: Arc<AtomicI32>,
+    iq_sinks: Vec<Arc<dyn IqSink>>,
+    processing_settings: dsp::ProcessingSettings,
+    recorder: Arc<Recorder>,
     sample_rate: Arc<AtomicU32>,
     should_stop: Arc<AtomicBool>,
-    ui: ui::Ui,
+    source: Arc<dyn IqSource>,
+    ui: Option<ui::Ui>,
 }
 
 impl WaterfallDemo {
     pub fn new(
-        center_frequency_hz: u32,
-        fft_window: dsp::WindowType,
+        source: Arc<dyn IqSource>,
+        processing_settings: dsp::ProcessingSettings,
+        recorder: Arc<Recorder>,
+        display_settings: ui::DisplaySettings,
+        headless: bool,
     ) -> WaterfallDemo {
-        let center_frequency = Arc::new(AtomicU32::new(center_frequency_hz));
+        let center_frequency =
+            Arc::new(AtomicU32::new(source.center_frequency()));
         let gain = Arc::new(AtomicI32::new(0));
-        let sample_rate: u32 = 2_400_000;
+        let sample_rate = Arc::new(AtomicU32::new(source.sample_rate()));
 
         WaterfallDemo {
             center_frequency: center_frequency.clone(),
             control_thread: None,
-            fft_window: fft_window,
+            fft_sinks: vec![],
             gain: gain.clone(),
-            sample_rate: Arc::new(AtomicU32::new(sample_rate)),
+            iq_sinks: vec![recorder.clone()],
+            processing_settings: processing_settings.clone(),
+            recorder: recorder.clone(),
+            sample_rate: sample_rate.clone(),
             should_stop: Arc::new(AtomicBool::new(false)),
-            ui: ui::Ui::new(
-                center_frequency.clone(),
-                gain.clone(),
-                sample_rate,
-            ),
+            source,
+            ui: (!headless).then(|| {
+                ui::Ui::new(
+                    center_frequency.clone(),
+                    gain.clone(),
+                    recorder,
+                    sample_rate,
+                    processing_settings.window.window_type.clone(),
+                    processing_settings.correction.clone(),
+                    display_settings,
+                )
+            }),
         }
     }
 
+    /// Adds a consumer for the results of the signal processing in headless
+    /// mode.
+    pub fn add_fft_sink(&mut self, sink: Box<dyn FftSink>) {
+        self.fft_sinks.push(sink);
+    }
+
     pub fn run(&mut self) {
-        self.start_control_thread();
-        self.start_sdl2_window();
+        let receiver = self.start_control_thread();
+        if self.ui.is_some() {
+            self.start_sdl2_window(receiver);
+        } else {
+            self.run_headless(receiver);
+        }
         self.finish();
     }
 
+    /// Shares the IQ stream with `rtl_tcp` clients, which may also tune the
+    /// source.
+    pub fn start_rtl_tcp_server<A: ToSocketAddrs>(
+        &mut self,
+        address: A,
+    ) -> io::Result<()> {
+        let server = RtlTcpServer::start(
+            address,
+            self.source.tuner(),
+            self.source.available_gains(),
+            self.center_frequency.clone(),
+            self.gain.clone(),
+            self.sample_rate.clone(),
+        )?;
+        self.iq_sinks.push(server);
+
+        Ok(())
+    }
+
     fn finish(&mut self) {
         self.should_stop.store(true, Ordering::Relaxed);
         if let Some(thread) = self.control_thread.take() {
             thread.join().unwrap();
         }
+        if let Err(error) = self.recorder.stop() {
+            eprintln!("Could not write the recording: {error}");
+        }
         self.should_stop.store(false, Ordering::Relaxed);
         self.control_thread = None;
     }
 
-    fn start_control_thread(&mut self) {
+    fn run_headless(&mut self, receiver: Receiver<dsp::FftResult>) {
+        headless::run(receiver, &mut self.fft_sinks, self.should_stop.clone());
+        self.should_stop.store(true, Ordering::Relaxed);
+    }
+
+    fn start_control_thread(&mut self) -> Receiver<dsp::FftResult> {
         let center_frequency = self.center_frequency.clone();
-        let fft_window = self.fft_window;
+        let processing_settings = self.processing_settings.clone();
+        let offset = processing_settings.tuning_offset;
         let gain = self.gain.clone();
+        let iq_sinks = self.iq_sinks.clone();
         let sample_rate = self.sample_rate.clone();
         let should_stop = self.should_stop.clone();
-        let (sync_sender, receiver) = sync_channel::<dsp::FftResult>(0);
-        self.ui.set_fft_receiver(receiver);
-
-        let (mut ctl, reader) = rtlsdr_mt::open(0)
-            .expect("Could not open RTL-SDR device at index 0.");
-        ctl.set_sample_rate(sample_rate.load(Ordering::Relaxed))
-            .unwrap();
-        ctl.set_center_freq(center_frequency.load(Ordering::Relaxed))
-            .unwrap();
-        ctl.disable_agc().unwrap();
-        ctl.set_tuner_gain(gain.load(Ordering::Relaxed)).unwrap();
-
-        let mut gains = [0i32; 32];
-        ctl.tuner_gains(&mut gains);
-        self.ui.set_available_gains(gains.to_vec());
+        // A single result is buffered, so that a result finished while the
+        // user interface draws is not lost.
+        let (sync_sender, receiver) = sync_channel::<dsp::FftResult>(1);
+
+        let source = self.source.clone();
+        source.set_gain(gain.load(Ordering::Relaxed)).unwrap();
+        if let Some(ui) = self.ui.as_mut() {
+            ui.set_available_gains(source.available_gains());
+            ui.set_tuner(source.tuner());
+        }
 
         self.control_thread = Some(thread::spawn(move || {
             let reader_thread = dsp::start_reader_thread(
-                reader,
-                center_frequency.clone(),
-                fft_window,
+                source.clone(),
+                processing_settings,
+                iq_sinks,
                 should_stop.clone(),
                 sync_sender,
             );
 
             while !should_stop.load(Ordering::Relaxed) {
-                let desired_freq = center_frequency.load(Ordering::Relaxed);
-                let current_freq = ctl.center_freq();
+                let desired_freq = center_frequency
+                    .load(Ordering::Relaxed)
+                    .saturating_add_signed(offset);
+                let current_freq = source.center_frequency();
 
                 if current_freq != desired_freq {
-                    ctl.cancel_async_read();
-                    ctl.set_center_freq(desired_freq).unwrap();
+                    source.cancel_async_read();
+                    if source.set_center_frequency(desired_freq).is_err() {
+                        center_frequency.store(
+                            current_freq.saturating_add_signed(-offset),
+                            Ordering::Relaxed,
+                        );
+                    }
                 }
                 let desired_gain = gain.load(Ordering::Relaxed);
-                let current_gain = ctl.tuner_gain();
+                let current_gain = source.gain();
 
-                if current_gain != desired_gain {
-                    ctl.set_tuner_gain(desired_gain).unwrap();
+                if current_gain != desired_gain
+                    && source.set_gain(desired_gain).is_err()
+                {
+                    gain.store(current_gain, Ordering::Relaxed);
+                }
+                let desired_rate = sample_rate.load(Ordering::Relaxed);
+                let current_rate = source.sample_rate();
+
+                if current_rate != desired_rate {
+                    source.cancel_async_read();
+                    if source.set_sample_rate(desired_rate).is_err() {
+                        sample_rate.store(current_rate, Ordering::Relaxed);
+                    }
                 }
                 thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
             }
-            ctl.cancel_async_read();
+            source.cancel_async_read();
             reader_thread.join().unwrap();
         }));
+
+        receiver
     }
 
-    fn start_sdl2_window(&mut self) {
-        self.ui.run();
+    fn start_sdl2_window(&mut self, receiver: Receiver<dsp::FftResult>) {
+        if let Some(ui) = self.ui.as_mut() {
+            ui.set_fft_receiver(receiver);
+            ui.run();
+        }
         self.should_stop.store(true, Ordering::Relaxed);
     }
 }
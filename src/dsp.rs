@@ -16,129 +16,408 @@
  *   along with rust-rtl-sdr-waterfall-demo. If not, see
  *   <https://www.gnu.org/licenses/>. */
 
+use crate::source::{IqSink, IqSource};
+use correction::{DcBlocker, IqBalancer};
+use detection::detect_signals;
+pub use detection::Signal;
 use rustfft::{num_complex::Complex, Fft, FftPlanner};
-use std::ops::DerefMut;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::SyncSender;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
+use window::Window;
+pub use window::{Normalization, WindowType};
 
-const FFT_SIZE: usize = crate::ui::WIDTH as usize;
+mod correction;
+mod detection;
+mod window;
+
+pub const MIN_FFT_SIZE: usize = 256;
+pub const MAX_FFT_SIZE: usize = 65536;
 
 pub fn start_reader_thread(
-    mut reader: rtlsdr_mt::Reader,
-    center_frequency: Arc<AtomicU32>,
-    fft_window: WindowType,
+    source: Arc<dyn IqSource>,
+    settings: ProcessingSettings,
+    iq_sinks: Vec<Arc<dyn IqSink>>,
     should_stop: Arc<AtomicBool>,
     sender: SyncSender<FftResult>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
-        let signal_processor = SignalProcessor::new(fft_window);
+        let fft_size = settings.fft_size;
+        let tuning_offset = settings.tuning_offset;
+        let mut signal_processor = SignalProcessor::new(settings);
 
         while !should_stop.load(Ordering::Relaxed) {
-            let cf = center_frequency.load(Ordering::Relaxed);
-            match reader.read_async(1, 2048, |buf| {
-                let mut result = signal_processor.process_signal(buf);
-                result.center_frequency = cf;
-                match sender.try_send(result) {
-                    Ok(..) => {}
-                    Err(..) => {}
-                }
-            }) {
+            match source.read_async(
+                fft_size * 2,
+                &mut |buf, cf, sample_rate| {
+                    for sink in &iq_sinks {
+                        sink.write(buf, cf);
+                    }
+                    // The spectrum is shifted back to the desired frequency.
+                    let displayed_cf = cf.saturating_add_signed(-tuning_offset);
+                    if displayed_cf != signal_processor.center_frequency
+                        || sample_rate != signal_processor.sample_rate
+                    {
+                        // Samples from before the retuning must not end up in
+                        // the averages.
+                        signal_processor.reset(displayed_cf, sample_rate);
+                    }
+                    for result in signal_processor.process_signal(buf) {
+                        // Results are dropped while the UI is busy.
+                        let _ = sender.try_send(result);
+                    }
+                },
+            ) {
                 Ok(..) => {}
-                Err(..) => {}
+                // The end of a recording has been reached or the server has
+                // closed the connection.
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::BrokenPipe
+                            | io::ErrorKind::ConnectionAborted
+                            | io::ErrorKind::ConnectionReset
+                            | io::ErrorKind::NotConnected
+                    ) =>
+                {
+                    eprintln!("The connection to the source was lost: {e}");
+                    break;
+                }
+                Err(e) => {
+                    eprintln!("Reading from the source failed: {e}");
+                    // Retrying right away would keep a core busy.
+                    thread::sleep(Duration::from_millis(100));
+                }
             }
         }
     })
 }
 
-#[derive(clap::ValueEnum, Clone, Copy, Debug)]
-pub enum WindowType {
-    Bartlett,
-    Rectangular,
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AveragingMode {
+    /// Every result shows a single FFT frame
+    None,
+    /// The mean power of the last frames
+    Linear,
+    /// Exponential smoothing of the power
+    Exponential,
+}
+
+/// How the FFT frames are cut from the signal and combined into results.
+#[derive(Clone, Copy)]
+pub struct AveragingSettings {
+    /// The weight of the newest frame for exponential averaging.
+    pub alpha: f64,
+    /// The number of frames for linear averaging.
+    pub frames: usize,
+    pub mode: AveragingMode,
+    /// The fraction of each frame that is shared with the next frame.
+    pub overlap: f64,
+    /// The number of results per second of signal. With zero every frame
+    /// produces a result.
+    pub result_rate: f64,
+}
+
+/// The corrections of the RTL-SDR signal, which may be toggled by the user
+/// interface while the FFT is running.
+#[derive(Clone)]
+pub struct CorrectionSettings {
+    /// Remove the DC offset, which shows as a spike at the center frequency.
+    pub dc_block: Arc<AtomicBool>,
+    /// Correct the gain and phase imbalance between I and Q, which shows as
+    /// mirror images of strong signals.
+    pub iq_balance: Arc<AtomicBool>,
+}
+
+/// What is considered a signal rather than noise.
+#[derive(Clone, Copy)]
+pub struct DetectionSettings {
+    /// The minimum distance in Hz between two signals.
+    pub min_separation: f64,
+    /// The percentile of the power of the bins taken as the noise floor.
+    pub noise_percentile: f64,
+    /// How many dB a signal must be above the noise floor.
+    pub peak_threshold: f64,
 }
 
 pub struct FftResult {
-    pub avg: f64,
     pub center_frequency: u32,
     pub log_magnitudes: Vec<f64>,
-    pub peak: Option<(usize, f64)>,
+    /// The noise floor in dBFS, estimated from the power of the bins.
+    pub noise_floor: f64,
+    pub sample_rate: u32,
+    /// The detected signals, strongest first.
+    pub signals: Vec<Signal>,
+}
+
+/// Everything that determines how the signal is turned into spectra.
+#[derive(Clone)]
+pub struct ProcessingSettings {
+    pub averaging: AveragingSettings,
+    pub correction: CorrectionSettings,
+    pub detection: DetectionSettings,
+    pub fft_size: usize,
+    /// How far in Hz the source is tuned away from the displayed center
+    /// frequency, so that the DC offset of the tuner is off center.
+    pub tuning_offset: i32,
+    pub window: WindowSettings,
+}
+
+/// The window function and how the FFT is scaled.
+#[derive(Clone)]
+pub struct WindowSettings {
+    pub kaiser_beta: f64,
+    pub normalization: Normalization,
+    /// May be changed by the user interface while the FFT is running.
+    pub window_type: Arc<Mutex<WindowType>>,
 }
 
 struct SignalProcessor {
+    averaging_settings: AveragingSettings,
+    /// The center frequency of the displayed spectrum.
+    center_frequency: u32,
+    correction_settings: CorrectionSettings,
+    dc_blocker: DcBlocker,
+    detection_settings: DetectionSettings,
     fft: Arc<dyn Fft<f64>>,
-    window: WindowType,
+    fft_size: usize,
+    /// The power spectra of the last frames for linear averaging.
+    history: VecDeque<Vec<f64>>,
+    iq_balancer: IqBalancer,
+    /// The phase of the oscillator shifting the signal by the tuning offset.
+    nco_phase: f64,
+    /// The averaged power spectrum in the order of the FFT output.
+    power: Option<Vec<f64>>,
+    power_scale: f64,
+    sample_rate: u32,
+    /// The samples that are not yet completely processed.
+    samples: Vec<Complex<f64>>,
+    samples_since_result: usize,
+    /// The sum of the power spectra in `history`.
+    sum: Vec<f64>,
+    tuning_offset: i32,
+    window: Window,
+    window_settings: WindowSettings,
+    window_type: WindowType,
 }
 
 impl SignalProcessor {
-    pub fn new(window: WindowType) -> SignalProcessor {
+    pub fn new(settings: ProcessingSettings) -> SignalProcessor {
         let mut planner: FftPlanner<f64> = FftPlanner::new();
-        let fft = planner.plan_fft_forward(FFT_SIZE);
+        let fft = planner.plan_fft_forward(settings.fft_size);
+        let window_type = *settings.window.window_type.lock().unwrap();
+        let window = Window::new(
+            window_type,
+            settings.fft_size,
+            settings.window.kaiser_beta,
+        );
 
         SignalProcessor {
-            fft: fft,
-            window: window,
+            averaging_settings: settings.averaging,
+            center_frequency: 0,
+            correction_settings: settings.correction,
+            dc_blocker: DcBlocker::default(),
+            detection_settings: settings.detection,
+            fft,
+            fft_size: settings.fft_size,
+            history: VecDeque::new(),
+            iq_balancer: IqBalancer::new(),
+            nco_phase: 0.,
+            power: None,
+            power_scale: window.power_scale(settings.window.normalization),
+            sample_rate: 0,
+            samples: vec![],
+            samples_since_result: 0,
+            sum: vec![],
+            tuning_offset: settings.tuning_offset,
+            window,
+            window_settings: settings.window,
+            window_type,
         }
     }
 
-    pub fn process_signal(&self, buf: &[u8]) -> FftResult {
-        let mut signal_vector = buf
-            .chunks(2)
-            .map(|pair| Complex {
+    /// Cuts the signal into overlapping frames and returns a result whenever
+    /// one is due according to the result rate.
+    pub fn process_signal(&mut self, buf: &[u8]) -> Vec<FftResult> {
+        let window_type = *self.window_settings.window_type.lock().unwrap();
+        if window_type != self.window_type {
+            self.update_window(window_type);
+        }
+        let dc_block =
+            self.correction_settings.dc_block.load(Ordering::Relaxed);
+        let iq_balance =
+            self.correction_settings.iq_balance.load(Ordering::Relaxed);
+        let nco_step =
+            TAU * self.tuning_offset as f64 / self.sample_rate as f64;
+        for pair in buf.chunks(2) {
+            let mut sample = Complex {
                 re: (f64::from(pair[0]) - 127.0) / 127.0,
                 im: (f64::from(pair[1]) - 127.0) / 127.0,
-            })
-            .collect::<Vec<Complex<f64>>>();
-        let signal = signal_vector.deref_mut();
-        self.apply_window(signal);
-        return self.work_fft(signal);
+            };
+            // The IQ balance is estimated on the signal without the offset.
+            if dc_block {
+                sample = self.dc_blocker.process(sample);
+            }
+            if iq_balance {
+                sample = self.iq_balancer.process(sample);
+            }
+            if self.tuning_offset != 0 {
+                sample *= Complex::from_polar(1., self.nco_phase);
+                self.nco_phase = (self.nco_phase + nco_step) % TAU;
+            }
+            self.samples.push(sample);
+        }
+
+        let overlap = (self.fft_size as f64 * self.averaging_settings.overlap)
+            .round() as usize;
+        let hop = (self.fft_size - overlap).max(1);
+        let result_interval = if self.averaging_settings.result_rate > 0. {
+            (self.sample_rate as f64 / self.averaging_settings.result_rate)
+                as usize
+        } else {
+            0
+        };
+        let mut results = vec![];
+        let mut start = 0;
+
+        while start + self.fft_size <= self.samples.len() {
+            self.samples_since_result += hop;
+            let due = self.samples_since_result >= result_interval;
+            // Without averaging only the frames for the results are needed.
+            if due || self.averaging_settings.mode != AveragingMode::None {
+                let power = self
+                    .power_spectrum(&self.samples[start..][..self.fft_size]);
+                self.average(power);
+            }
+            if due {
+                self.samples_since_result = 0;
+                results.push(self.work_result());
+            }
+            start += hop;
+        }
+        self.samples.drain(..start.min(self.samples.len()));
+
+        results
     }
 
-    fn apply_window(&self, signal: &mut [Complex<f64>]) {
-        match self.window {
-            WindowType::Bartlett => {
-                for i in 0..FFT_SIZE / 2 {
-                    let factor = i as f64 / (FFT_SIZE / 2) as f64;
-                    signal[i] *= factor;
-                    signal[FFT_SIZE - i - 1] *= factor;
+    /// Discards all samples and averages, for example after retuning.
+    pub fn reset(&mut self, center_frequency: u32, sample_rate: u32) {
+        self.center_frequency = center_frequency;
+        self.history.clear();
+        self.nco_phase = 0.;
+        self.power = None;
+        self.sample_rate = sample_rate;
+        self.samples.clear();
+        self.samples_since_result = 0;
+        self.sum.clear();
+    }
+
+    fn average(&mut self, power: Vec<f64>) {
+        match self.averaging_settings.mode {
+            AveragingMode::None => self.power = Some(power),
+            AveragingMode::Linear => {
+                self.sum.resize(power.len(), 0.);
+                for (s, p) in self.sum.iter_mut().zip(&power) {
+                    *s += p;
+                }
+                self.history.push_back(power);
+                while self.history.len() > self.averaging_settings.frames {
+                    let oldest = self.history.pop_front().unwrap();
+                    for (s, p) in self.sum.iter_mut().zip(&oldest) {
+                        *s -= p;
+                    }
                 }
+                let frames = self.history.len() as f64;
+                // Rounding errors of the running sum must not turn negative.
+                self.power = Some(
+                    self.sum.iter().map(|s| (s / frames).max(0.)).collect(),
+                );
             }
-            WindowType::Rectangular => {
-                // Do nothing
+            AveragingMode::Exponential => {
+                let alpha = self.averaging_settings.alpha;
+                self.power = Some(match self.power.take() {
+                    Some(average) => average
+                        .iter()
+                        .zip(&power)
+                        .map(|(a, p)| alpha * p + (1. - alpha) * a)
+                        .collect(),
+                    None => power,
+                });
             }
         }
     }
 
-    fn work_fft(&self, signal: &mut [Complex<f64>]) -> FftResult {
-        self.fft.process(signal);
+    /// The windowed and scaled power of each bin of a single frame.
+    fn power_spectrum(&self, frame: &[Complex<f64>]) -> Vec<f64> {
+        let mut signal = frame.to_vec();
+        self.window.apply(&mut signal);
+        self.fft.process(&mut signal);
 
-        let mut result = FftResult {
-            avg: 0.0f64,
-            center_frequency: 0,
-            log_magnitudes: vec![0.0f64; FFT_SIZE],
-            peak: None,
-        };
-        let mut peak: (usize, f64) = (0, -120.0);
-
-        for (i, c) in signal.into_iter().enumerate() {
-            let index = (i + FFT_SIZE / 2) % FFT_SIZE;
-	    // Clip the magnitude between 0 and 120 dBFS
-            let logmag = 10.0
-                * (c.norm_sqr() / (FFT_SIZE as f64).powi(2))
-                    .max(1e-12)
-                    .log10()
-                    .min(0.);
-            result.log_magnitudes[index] = logmag;
-            result.avg += logmag;
-            if logmag > peak.1 {
-                peak = (index, logmag);
-            }
+        signal
+            .iter()
+            .map(|c| c.norm_sqr() * self.power_scale)
+            .collect()
+    }
+
+    /// Recomputes the coefficients for a newly selected window.
+    fn update_window(&mut self, window_type: WindowType) {
+        self.window_type = window_type;
+        self.window = Window::new(
+            window_type,
+            self.fft_size,
+            self.window_settings.kaiser_beta,
+        );
+        self.power_scale =
+            self.window.power_scale(self.window_settings.normalization);
+    }
+
+    fn work_result(&self) -> FftResult {
+        let power = self.power.as_deref().unwrap_or_default();
+        let noise_floor = log_magnitude(percentile(
+            power,
+            self.detection_settings.noise_percentile,
+        ));
+        let mut log_magnitudes = vec![0.0f64; self.fft_size];
+        for (i, p) in power.iter().enumerate() {
+            log_magnitudes[(i + self.fft_size / 2) % self.fft_size] =
+                log_magnitude(*p);
         }
-        result.avg /= FFT_SIZE as f64;
-        if peak.1 > result.avg + 20.0 {
-            result.peak = Some(peak);
+        let signals = detect_signals(
+            &log_magnitudes,
+            noise_floor,
+            &self.detection_settings,
+            self.center_frequency,
+            self.sample_rate,
+        );
+
+        FftResult {
+            center_frequency: self.center_frequency,
+            log_magnitudes,
+            noise_floor,
+            sample_rate: self.sample_rate,
+            signals,
         }
+    }
+}
 
-        return result;
+/// The power in dBFS, clipped between -120 and 0 dBFS.
+fn log_magnitude(power: f64) -> f64 {
+    10.0 * power.max(1e-12).log10().min(0.)
+}
+
+/// The value below which the given fraction of the values lies.
+fn percentile(values: &[f64], fraction: f64) -> f64 {
+    if values.is_empty() {
+        return 0.;
     }
+    let mut values = values.to_vec();
+    let index = ((values.len() - 1) as f64 * fraction).round() as usize;
+
+    *values.select_nth_unstable_by(index, f64::total_cmp).1
 }
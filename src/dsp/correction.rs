@@ -0,0 +1,88 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use rustfft::num_complex::Complex;
+
+/// The pole of the DC blocker. The closer to one, the narrower the notch.
+const DC_POLE: f64 = 0.9995;
+/// The weight of a new sample for the running estimates of the IQ balance.
+const IQ_ALPHA: f64 = 1e-4;
+
+/// Removes the DC offset with the high-pass `y[n] = x[n] - x[n-1] + R y[n-1]`.
+#[derive(Default)]
+pub struct DcBlocker {
+    input: Complex<f64>,
+    output: Complex<f64>,
+}
+
+/// Corrects the gain and phase imbalance between I and Q without a known
+/// signal.
+///
+/// A balanced signal has the same power on I and Q, which are uncorrelated.
+/// Therefore Q is first freed from its correlation with I and then scaled to
+/// the power of I.
+pub struct IqBalancer {
+    /// The running mean of I * I.
+    ii: f64,
+    /// The running mean of I * Q.
+    iq: f64,
+    /// The running mean of Q * Q.
+    qq: f64,
+}
+
+impl DcBlocker {
+    pub fn process(&mut self, sample: Complex<f64>) -> Complex<f64> {
+        self.output = sample - self.input + self.output * DC_POLE;
+        self.input = sample;
+
+        self.output
+    }
+}
+
+impl IqBalancer {
+    pub fn new() -> IqBalancer {
+        IqBalancer {
+            ii: 1.,
+            iq: 0.,
+            qq: 1.,
+        }
+    }
+
+    pub fn process(&mut self, sample: Complex<f64>) -> Complex<f64> {
+        let (i, q) = (sample.re, sample.im);
+        self.ii += IQ_ALPHA * (i * i - self.ii);
+        self.iq += IQ_ALPHA * (i * q - self.iq);
+        self.qq += IQ_ALPHA * (q * q - self.qq);
+        if self.ii <= f64::EPSILON {
+            return sample;
+        }
+
+        let correlation = self.iq / self.ii;
+        // The power of Q after the correlation with I has been removed.
+        let orthogonal_power = self.qq - correlation * self.iq;
+        if orthogonal_power <= f64::EPSILON {
+            return sample;
+        }
+        let scale = (self.ii / orthogonal_power).sqrt();
+
+        Complex {
+            re: i,
+            im: (q - correlation * i) * scale,
+        }
+    }
+}
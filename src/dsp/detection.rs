@@ -0,0 +1,115 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use super::DetectionSettings;
+
+/// A local maximum of the spectrum above the noise floor.
+#[derive(Clone, Debug)]
+pub struct Signal {
+    /// The width in Hz where the power is at most 3 dB below the maximum.
+    pub bandwidth_3db: f64,
+    /// The width in Hz where the power is at most 6 dB below the maximum.
+    pub bandwidth_6db: f64,
+    /// The interpolated frequency of the maximum in Hz.
+    pub frequency: f64,
+    /// The interpolated power of the maximum in dBFS.
+    pub power: f64,
+}
+
+/// Finds the local maxima that exceed the noise floor by the peak threshold,
+/// strongest first.
+///
+/// A maximum closer than the minimum separation to a stronger signal is
+/// considered a part of that signal.
+pub fn detect_signals(
+    log_magnitudes: &[f64],
+    noise_floor: f64,
+    settings: &DetectionSettings,
+    center_frequency: u32,
+    sample_rate: u32,
+) -> Vec<Signal> {
+    let bins = log_magnitudes.len();
+    let bin_width = sample_rate as f64 / bins as f64;
+    let threshold = noise_floor + settings.peak_threshold;
+    let mut maxima: Vec<usize> = (0..bins)
+        .filter(|&bin| {
+            let logmag = log_magnitudes[bin];
+            logmag > threshold
+                && (bin == 0 || log_magnitudes[bin - 1] <= logmag)
+                && (bin == bins - 1 || log_magnitudes[bin + 1] < logmag)
+        })
+        .collect();
+    maxima.sort_by(|a, b| log_magnitudes[*b].total_cmp(&log_magnitudes[*a]));
+
+    let mut signals: Vec<Signal> = vec![];
+    for bin in maxima {
+        let (offset, power) = interpolate(log_magnitudes, bin);
+        let frequency = center_frequency as f64
+            + (bin as f64 + offset - (bins / 2) as f64) * bin_width;
+        if signals.iter().any(|signal| {
+            (signal.frequency - frequency).abs() < settings.min_separation
+        }) {
+            continue;
+        }
+        signals.push(Signal {
+            bandwidth_3db: width(log_magnitudes, bin, 3.) as f64 * bin_width,
+            bandwidth_6db: width(log_magnitudes, bin, 6.) as f64 * bin_width,
+            frequency,
+            power,
+        });
+    }
+
+    signals
+}
+
+/// Estimates the offset in bins and the power of a maximum between the bins.
+///
+/// A parabola through the logarithmic magnitudes of the bin and its neighbors
+/// equals a Gaussian through their power, which approximates the main lobe of
+/// the common windows well.
+fn interpolate(log_magnitudes: &[f64], bin: usize) -> (f64, f64) {
+    let center = log_magnitudes[bin];
+    if bin == 0 || bin == log_magnitudes.len() - 1 {
+        return (0., center);
+    }
+    let (left, right) = (log_magnitudes[bin - 1], log_magnitudes[bin + 1]);
+    let curvature = left - 2. * center + right;
+    if curvature >= 0. {
+        return (0., center);
+    }
+    let offset = 0.5 * (left - right) / curvature;
+
+    (offset, center - 0.25 * (left - right) * offset)
+}
+
+/// The number of contiguous bins around `bin` that are at most `drop` dB
+/// below it.
+fn width(log_magnitudes: &[f64], bin: usize, drop: f64) -> usize {
+    let level = log_magnitudes[bin] - drop;
+    let below = log_magnitudes[..bin]
+        .iter()
+        .rev()
+        .take_while(|logmag| **logmag >= level)
+        .count();
+    let above = log_magnitudes[bin + 1..]
+        .iter()
+        .take_while(|logmag| **logmag >= level)
+        .count();
+
+    below + 1 + above
+}
@@ -0,0 +1,157 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use clap::ValueEnum;
+use rustfft::num_complex::Complex;
+use std::f64::consts::PI;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum WindowType {
+    Bartlett,
+    Blackman,
+    BlackmanHarris,
+    FlatTop,
+    Hamming,
+    Hann,
+    Kaiser,
+    Nuttall,
+    Rectangular,
+}
+
+impl WindowType {
+    pub fn from_index(index: usize) -> WindowType {
+        let variants = WindowType::value_variants();
+        variants[index % variants.len()]
+    }
+
+    /// The position in `WindowType::value_variants`.
+    pub fn index(&self) -> usize {
+        WindowType::value_variants()
+            .iter()
+            .position(|window_type| window_type == self)
+            .unwrap()
+    }
+}
+
+/// How the power of the FFT bins is scaled, see `docs/fft_normalization.md`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Normalization {
+    /// Correct the coherent gain, so that a sinusoid has the same level with
+    /// every window
+    Amplitude,
+    /// Correct the equivalent noise bandwidth, so that the noise floor has
+    /// the same level with every window
+    Noise,
+}
+
+/// The precomputed coefficients of a window function.
+pub struct Window {
+    coefficients: Vec<f64>,
+}
+
+impl Window {
+    /// Computes the periodic window of the given size. The parameter `beta`
+    /// is only used by the Kaiser window.
+    pub fn new(window_type: WindowType, size: usize, beta: f64) -> Window {
+        let coefficients = match window_type {
+            WindowType::Bartlett => (0..size)
+                .map(|i| 1. - (2. * i as f64 / size as f64 - 1.).abs())
+                .collect(),
+            WindowType::Blackman => cosine_sum(&[0.42, 0.5, 0.08], size),
+            WindowType::BlackmanHarris => {
+                cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168], size)
+            }
+            WindowType::FlatTop => cosine_sum(
+                &[
+                    0.21557895,
+                    0.41663158,
+                    0.277263158,
+                    0.083578947,
+                    0.006947368,
+                ],
+                size,
+            ),
+            WindowType::Hamming => cosine_sum(&[0.54, 0.46], size),
+            WindowType::Hann => cosine_sum(&[0.5, 0.5], size),
+            WindowType::Kaiser => (0..size)
+                .map(|i| {
+                    let x = 2. * i as f64 / size as f64 - 1.;
+                    bessel_i0(beta * (1. - x * x).sqrt()) / bessel_i0(beta)
+                })
+                .collect(),
+            WindowType::Nuttall => {
+                cosine_sum(&[0.355768, 0.487396, 0.144232, 0.012604], size)
+            }
+            WindowType::Rectangular => vec![1.; size],
+        };
+
+        Window { coefficients }
+    }
+
+    pub fn apply(&self, signal: &mut [Complex<f64>]) {
+        for (sample, coefficient) in signal.iter_mut().zip(&self.coefficients) {
+            *sample *= coefficient;
+        }
+    }
+
+    /// The factor to scale the squared magnitudes of the FFT to dBFS.
+    ///
+    /// For the rectangular window both normalizations result in
+    /// `1 / (fft_size * fft_size)`.
+    pub fn power_scale(&self, normalization: Normalization) -> f64 {
+        match normalization {
+            Normalization::Amplitude => {
+                1. / self.coefficients.iter().sum::<f64>().powi(2)
+            }
+            Normalization::Noise => {
+                1. / (self.coefficients.len() as f64
+                    * self.coefficients.iter().map(|w| w * w).sum::<f64>())
+            }
+        }
+    }
+}
+
+/// A window of the form `a0 - a1 cos(2πn/N) + a2 cos(4πn/N) - ...`.
+fn cosine_sum(a: &[f64], size: usize) -> Vec<f64> {
+    (0..size)
+        .map(|n| {
+            a.iter()
+                .enumerate()
+                .map(|(k, a_k)| {
+                    let sign = if k % 2 == 0 { 1. } else { -1. };
+                    sign * a_k
+                        * (2. * PI * k as f64 * n as f64 / size as f64).cos()
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// The modified Bessel function of the first kind of order zero.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.;
+    let mut term = 1.;
+    let mut k = 1.;
+    while term > sum * 1e-12 {
+        term *= (x / (2. * k)).powi(2);
+        sum += term;
+        k += 1.;
+    }
+
+    sum
+}
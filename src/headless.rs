@@ -0,0 +1,148 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use crate::dsp::FftResult;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Consumes the results of the signal processing when there is no window.
+pub trait FftSink {
+    fn consume(&mut self, result: &FftResult) -> io::Result<()>;
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Prints a summary of the spectrum to stdout at a fixed interval.
+pub struct LogSink {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+/// Writes each spectrum as a line of comma separated values, starting with
+/// the unix time in milliseconds and the center frequency followed by the
+/// magnitudes in dBFS.
+pub struct FileSink {
+    writer: BufWriter<File>,
+}
+
+impl LogSink {
+    pub fn new(interval: Duration) -> LogSink {
+        LogSink {
+            interval,
+            last: None,
+        }
+    }
+}
+
+impl FftSink for LogSink {
+    fn consume(&mut self, result: &FftResult) -> io::Result<()> {
+        if self.last.is_some_and(|last| last.elapsed() < self.interval) {
+            return Ok(());
+        }
+        self.last = Some(Instant::now());
+
+        let freq_mhz = result.center_frequency as f64 / 1_000_000.;
+        let floor = result.noise_floor;
+        let mut line = format!("{freq_mhz:.3} MHz  Floor: {floor:.1} dBFS");
+        if let Some(signal) = result.signals.first() {
+            let peak_mhz = signal.frequency / 1_000_000.;
+            line += &format!(
+                "  Peak: {:.1} dBFS @ {peak_mhz:.4} MHz",
+                signal.power
+            );
+        }
+        line += &format!("  Signals: {}", result.signals.len());
+        writeln!(io::stdout(), "{line}")
+    }
+}
+
+impl FileSink {
+    pub fn create(path: &Path) -> io::Result<FileSink> {
+        Ok(FileSink {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+}
+
+impl FftSink for FileSink {
+    fn consume(&mut self, result: &FftResult) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        write!(self.writer, "{timestamp},{}", result.center_frequency)?;
+        for logmag in &result.log_magnitudes {
+            write!(self.writer, ",{logmag:.1}")?;
+        }
+        writeln!(self.writer)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Passes the results to the sinks until `should_stop` is set by SIGINT or
+/// SIGTERM, the reader thread ends at the end of a recording or a sink fails,
+/// for example because stdout was closed.
+pub fn run(
+    receiver: Receiver<FftResult>,
+    sinks: &mut [Box<dyn FftSink>],
+    should_stop: Arc<AtomicBool>,
+) {
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, should_stop.clone())
+            .expect("Could not register the signal handler.");
+    }
+
+    while !should_stop.load(Ordering::Relaxed) {
+        match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(result) => {
+                let failed = sinks.iter_mut().any(|sink| {
+                    sink.consume(&result).map_err(report_error).is_err()
+                });
+                if failed {
+                    break;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for sink in sinks.iter_mut() {
+        if let Err(error) = sink.finish() {
+            report_error(error);
+        }
+    }
+}
+
+/// Prints the error of a sink, except for a closed pipe like `| head`, which
+/// is the regular way to stop reading.
+fn report_error(error: io::Error) {
+    if error.kind() != io::ErrorKind::BrokenPipe {
+        eprintln!("Could not write the FFT: {error}");
+    }
+}
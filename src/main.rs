@@ -16,27 +16,381 @@
  *   along with rust-rtl-sdr-waterfall-demo. If not, see
  *   <https://www.gnu.org/licenses/>. */
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use headless::{FileSink, LogSink};
+use recorder::{Recorder, RecordingFormat};
+use source::file::FileSource;
+use source::rtl_tcp::RtlTcpSource;
+use source::rtlsdr::RtlSdrSource;
+use source::IqSource;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 mod demo;
 mod dsp;
+mod headless;
+mod recorder;
+mod rtl_tcp_server;
+mod sigmf;
+mod source;
 mod ui;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct CliArgs {
-    #[arg(short, long, default_value_t = 100.000)]
-    center_frequency_mhz: f64,
+    /// Let the levels of the spectrum and the waterfall follow the noise
+    /// floor, toggled with A
+    #[arg(long)]
+    auto_range: bool,
+    /// The weight of the newest frame for exponential averaging
+    #[arg(long, default_value_t = 0.3, value_parser = parse_alpha)]
+    average_alpha: f64,
+    /// The number of frames for linear averaging, at most 1024
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..=1024))]
+    average_frames: u16,
+    #[arg(long, value_enum, default_value_t = dsp::AveragingMode::None)]
+    averaging: dsp::AveragingMode,
+    /// Defaults to 100 MHz. Recordings default to the value from their
+    /// metadata and need this option if they have none
+    #[arg(short, long)]
+    center_frequency_mhz: Option<f64>,
+    /// The colors of the waterfall, cycled with C
+    #[arg(long, value_enum, default_value_t = ui::ColorMapType::Synthwave)]
+    color_map: ui::ColorMapType,
+    /// Load the colors of the waterfall from a file with one color per line,
+    /// either as #rrggbb or as r, g, b, from the weakest to the strongest level
+    #[arg(long)]
+    color_map_file: Option<PathBuf>,
+    /// Remove the DC spike at the center frequency, toggled with D
+    #[arg(long)]
+    dc_block: bool,
+    /// The span in dB below the reference level, from 10 to 120
+    #[arg(long, default_value_t = 120., value_parser = parse_dynamic_range)]
+    dynamic_range: f64,
+    #[arg(long, value_enum, default_value_t = dsp::Normalization::Amplitude)]
+    fft_normalization: dsp::Normalization,
     #[arg(short, long, value_enum, default_value_t=dsp::WindowType::Rectangular)]
     fft_window: dsp::WindowType,
+    /// The number of spectra per second of signal, or 0 for every frame
+    #[arg(long, default_value_t = 0.)]
+    fft_rate: f64,
+    /// A power of two from 256 to 65536
+    #[arg(long, default_value_t = 1024, value_parser = parse_fft_size)]
+    fft_size: usize,
+    /// Write every spectrum as comma separated values in headless mode
+    #[arg(long, requires = "headless")]
+    fft_output: Option<PathBuf>,
+    /// How the FFT bins are combined when there are more bins than pixels
+    #[arg(long, value_enum, default_value_t = ui::Decimation::Max)]
+    decimation: ui::Decimation,
+    /// How fast the max-hold and min-hold traces return to the spectrum in dB
+    /// per second, or 0 to hold forever
+    #[arg(long, default_value_t = 10.)]
+    hold_decay: f64,
+    /// Run without a window until SIGINT or SIGTERM is received
+    #[arg(long)]
+    headless: bool,
+    /// Replay a raw or SigMF recording of unsigned 8-bit IQ samples instead
+    /// of using the RTL-SDR
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Correct the gain and phase imbalance between I and Q, toggled with I
+    #[arg(long)]
+    iq_balance: bool,
+    /// The interval in seconds between log lines in headless mode
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        requires = "headless",
+        value_parser = parse_interval
+    )]
+    log_interval: f64,
+    /// The shape parameter of the Kaiser window
+    #[arg(long, default_value_t = 8.6, value_parser = parse_kaiser_beta)]
+    kaiser_beta: f64,
+    /// The minimum distance in Hz between two detected signals
+    #[arg(long, default_value_t = 10_000., value_parser = parse_min_separation)]
+    min_separation: f64,
+    /// Replay the recording as fast as possible instead of in real time
+    #[arg(long, requires = "input")]
+    no_pacing: bool,
+    /// The percentile of the power of the bins taken as the noise floor,
+    /// from 0 to 100
+    #[arg(long, default_value_t = 50., value_parser = parse_percentile)]
+    noise_percentile: f64,
+    /// Tune the source this many Hz away from the center frequency and shift
+    /// the spectrum back, which moves the DC spike off center
+    #[arg(
+        long,
+        default_value_t = 0,
+        conflicts_with_all = ["input", "rtl_tcp_server"],
+        allow_negative_numbers = true
+    )]
+    offset_tuning: i32,
+    /// The overlap of consecutive FFT frames in percent, from 0 to 95
+    #[arg(long, default_value_t = 0., value_parser = parse_overlap)]
+    overlap: f64,
+    /// How many dB a peak must be above the noise floor
+    #[arg(long, default_value_t = 20., value_parser = parse_peak_threshold)]
+    peak_threshold: f64,
+    /// Start recording the raw IQ samples right away
+    #[arg(short, long)]
+    record: bool,
+    /// The directory for recordings
+    #[arg(long, default_value = ".")]
+    recording_directory: PathBuf,
+    #[arg(long, value_enum, default_value_t = RecordingFormat::Sigmf)]
+    recording_format: RecordingFormat,
+    /// The level in dBFS at the top of the spectrum, from -100 to 0
+    #[arg(
+        long,
+        default_value_t = 0.,
+        allow_negative_numbers = true,
+        value_parser = parse_reference_level
+    )]
+    reference_level: f64,
+    /// Connect to an rtl_tcp server at HOST:PORT instead of using a local
+    /// RTL-SDR
+    #[arg(long, conflicts_with = "input")]
+    rtl_tcp: Option<String>,
+    /// Share the IQ stream with rtl_tcp clients listening on HOST:PORT, for
+    /// example 0.0.0.0:1234
+    #[arg(long)]
+    rtl_tcp_server: Option<String>,
+    /// From 225001 to 300000 or from 900001 to 3200000 Hz for the RTL-SDR.
+    /// Defaults to 2.4 MHz or the value from the metadata of the recording
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    sample_rate: Option<u32>,
+    /// The number of detected signals that are listed and marked
+    #[arg(long, default_value_t = 5)]
+    signals: usize,
 }
 
 fn main() {
     let args = CliArgs::parse();
+    let center_frequency = args
+        .center_frequency_mhz
+        .map(|mhz| (mhz * 1_000_000.) as u32);
 
-    demo::WaterfallDemo::new(
-        (args.center_frequency_mhz * 1_000_000.) as u32,
-        args.fft_window,
-    )
-    .run();
+    let source: Arc<dyn IqSource> = match args.input {
+        Some(path) => Arc::new(
+            FileSource::open(
+                &path,
+                center_frequency,
+                args.sample_rate,
+                !args.no_pacing,
+            )
+            .expect("Could not open the recording."),
+        ),
+        None => {
+            let sample_rate = args.sample_rate.unwrap_or(2_400_000);
+            if !source::is_valid_sample_rate(sample_rate) {
+                CliArgs::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        "The RTL2832U supports sample rates from 225001 to \
+                         300000 and from 900001 to 3200000 Hz.",
+                    )
+                    .exit();
+            }
+            // Otherwise the displayed center frequency is outside the band.
+            if args.offset_tuning.unsigned_abs() >= sample_rate / 2 {
+                CliArgs::command()
+                    .error(
+                        ErrorKind::ValueValidation,
+                        "The tuning offset must be below half the sample rate.",
+                    )
+                    .exit();
+            }
+            let source: Arc<dyn IqSource> = match args.rtl_tcp {
+                Some(address) => Arc::new(
+                    RtlTcpSource::connect(address)
+                        .expect("Could not connect to the rtl_tcp server."),
+                ),
+                None => Arc::new(
+                    RtlSdrSource::open(0)
+                        .expect("Could not open RTL-SDR device at index 0."),
+                ),
+            };
+            source
+                .set_center_frequency(center_frequency.unwrap_or(100_000_000))
+                .unwrap();
+            source.set_sample_rate(sample_rate).unwrap();
+            source
+        }
+    };
+
+    let recorder = Arc::new(Recorder::new(
+        args.recording_directory,
+        args.recording_format,
+        source.hardware(),
+        args.offset_tuning,
+    ));
+    if args.record {
+        recorder
+            .start(source.center_frequency(), source.sample_rate())
+            .expect("Could not start the recording.");
+    }
+
+    let mut demo = demo::WaterfallDemo::new(
+        source,
+        dsp::ProcessingSettings {
+            averaging: dsp::AveragingSettings {
+                alpha: args.average_alpha,
+                frames: args.average_frames.into(),
+                mode: args.averaging,
+                overlap: args.overlap / 100.,
+                result_rate: args.fft_rate.max(0.),
+            },
+            correction: dsp::CorrectionSettings {
+                dc_block: Arc::new(AtomicBool::new(args.dc_block)),
+                iq_balance: Arc::new(AtomicBool::new(args.iq_balance)),
+            },
+            detection: dsp::DetectionSettings {
+                min_separation: args.min_separation,
+                noise_percentile: args.noise_percentile / 100.,
+                peak_threshold: args.peak_threshold,
+            },
+            fft_size: args.fft_size,
+            tuning_offset: args.offset_tuning,
+            window: dsp::WindowSettings {
+                kaiser_beta: args.kaiser_beta,
+                normalization: args.fft_normalization,
+                window_type: Arc::new(Mutex::new(args.fft_window)),
+            },
+        },
+        recorder,
+        ui::DisplaySettings {
+            auto_range: args.auto_range,
+            color_map: args.color_map,
+            custom_color_map: args.color_map_file.map(|path| {
+                ui::ColorMap::load(&path)
+                    .expect("Could not load the color map.")
+            }),
+            decimation: args.decimation,
+            dynamic_range: args.dynamic_range,
+            hold_decay: args.hold_decay.max(0.),
+            reference_level: args.reference_level,
+            signal_count: args.signals,
+        },
+        args.headless,
+    );
+    if args.headless {
+        demo.add_fft_sink(Box::new(LogSink::new(Duration::from_secs_f64(
+            args.log_interval,
+        ))));
+    }
+    if let Some(path) = args.fft_output {
+        demo.add_fft_sink(Box::new(
+            FileSink::create(&path).expect("Could not create the FFT output."),
+        ));
+    }
+    if let Some(address) = args.rtl_tcp_server {
+        demo.start_rtl_tcp_server(address)
+            .expect("Could not start the rtl_tcp server.");
+    }
+    demo.run();
+}
+
+fn parse_alpha(s: &str) -> Result<f64, String> {
+    let alpha: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(alpha > 0. && alpha <= 1.) {
+        return Err("The alpha must be greater than 0 and at most 1.".into());
+    }
+
+    Ok(alpha)
+}
+
+fn parse_dynamic_range(s: &str) -> Result<f64, String> {
+    let range: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(10. ..=120.).contains(&range) {
+        return Err("The dynamic range must be from 10 to 120 dB.".into());
+    }
+
+    Ok(range)
+}
+
+fn parse_fft_size(s: &str) -> Result<usize, String> {
+    let fft_size: usize = s.parse().map_err(|e| format!("{e}"))?;
+    if !fft_size.is_power_of_two()
+        || !(dsp::MIN_FFT_SIZE..=dsp::MAX_FFT_SIZE).contains(&fft_size)
+    {
+        return Err(format!(
+            "The FFT size must be a power of two from {} to {}.",
+            dsp::MIN_FFT_SIZE,
+            dsp::MAX_FFT_SIZE
+        ));
+    }
+
+    Ok(fft_size)
+}
+
+fn parse_interval(s: &str) -> Result<f64, String> {
+    let interval: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(interval.is_finite() && interval > 0.) {
+        return Err("The interval must be a positive number of seconds.".into());
+    }
+
+    Ok(interval)
+}
+
+fn parse_kaiser_beta(s: &str) -> Result<f64, String> {
+    let beta: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(0. ..=50.).contains(&beta) {
+        return Err("The Kaiser beta must be from 0 to 50.".into());
+    }
+
+    Ok(beta)
+}
+
+fn parse_min_separation(s: &str) -> Result<f64, String> {
+    let separation: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(separation.is_finite() && separation >= 0.) {
+        return Err(
+            "The separation must be a non-negative number of Hz.".into()
+        );
+    }
+
+    Ok(separation)
+}
+
+fn parse_overlap(s: &str) -> Result<f64, String> {
+    let overlap: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(0. ..=95.).contains(&overlap) {
+        return Err("The overlap must be from 0 to 95 percent.".into());
+    }
+
+    Ok(overlap)
+}
+
+fn parse_peak_threshold(s: &str) -> Result<f64, String> {
+    let threshold: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(threshold.is_finite() && threshold >= 0.) {
+        return Err(
+            "The peak threshold must be a non-negative number of dB.".into()
+        );
+    }
+
+    Ok(threshold)
+}
+
+fn parse_percentile(s: &str) -> Result<f64, String> {
+    let percentile: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(0. ..=100.).contains(&percentile) {
+        return Err("The percentile must be from 0 to 100.".into());
+    }
+
+    Ok(percentile)
+}
+
+fn parse_reference_level(s: &str) -> Result<f64, String> {
+    let level: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(-100. ..=0.).contains(&level) {
+        return Err("The reference level must be from -100 to 0 dBFS.".into());
+    }
+
+    Ok(level)
 }
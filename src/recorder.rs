@@ -0,0 +1,220 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use crate::sigmf::{self, Capture, Metadata};
+use crate::source::file::Sidecar;
+use crate::source::IqSink;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum RecordingFormat {
+    /// The format of `rtl_sdr` with a sidecar file
+    Raw,
+    /// A SigMF recording with capture segments for every retuning
+    Sigmf,
+}
+
+/// Writes the raw IQ buffers to disk.
+///
+/// The buffers are handed over to a writer thread through an unbounded
+/// channel, so that a slow disk never blocks the reader thread and no samples
+/// are dropped. When writing fails, the recording ends and the error is kept
+/// for `take_error`.
+pub struct Recorder {
+    directory: PathBuf,
+    /// The error that ended the last recording.
+    error: Mutex<Option<io::Error>>,
+    format: RecordingFormat,
+    hardware: String,
+    recording: Mutex<Option<Recording>>,
+    /// The distance in Hz of the tuned from the displayed center frequency.
+    tuning_offset: i32,
+}
+
+struct Recording {
+    sender: Sender<(u32, Vec<u8>)>,
+    started: Instant,
+    writer_thread: thread::JoinHandle<io::Result<()>>,
+}
+
+impl Recorder {
+    pub fn new(
+        directory: PathBuf,
+        format: RecordingFormat,
+        hardware: String,
+        tuning_offset: i32,
+    ) -> Recorder {
+        Recorder {
+            directory,
+            error: Mutex::new(None),
+            format,
+            hardware,
+            recording: Mutex::new(None),
+            tuning_offset,
+        }
+    }
+
+    /// The time since the start of the current recording.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.recording
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|recording| recording.started.elapsed())
+    }
+
+    /// Starts a new recording unless one is already running.
+    ///
+    /// The recording is named after the current time and the settings of the
+    /// tuner, which are also stored in its metadata. The samples are recorded
+    /// as tuned, so the displayed `center_frequency` is moved by the tuning
+    /// offset.
+    pub fn start(
+        &self,
+        center_frequency: u32,
+        sample_rate: u32,
+    ) -> io::Result<()> {
+        let mut recording = self.recording.lock().unwrap();
+        if recording.is_some() {
+            return Ok(());
+        }
+        let center_frequency =
+            center_frequency.saturating_add_signed(self.tuning_offset);
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let name = self.directory.join(format!(
+            "iq_{timestamp}_{center_frequency}Hz_{sample_rate}sps"
+        ));
+        let (path, mut metadata) = match self.format {
+            RecordingFormat::Raw => {
+                let sidecar = Sidecar {
+                    center_frequency: Some(center_frequency),
+                    sample_rate: Some(sample_rate),
+                };
+                sidecar.write(&Sidecar::path(&name))?;
+                (name.with_extension("cu8"), None)
+            }
+            RecordingFormat::Sigmf => {
+                let mut metadata =
+                    Metadata::new(sample_rate, self.hardware.clone());
+                metadata.captures.push(Capture::new(0, center_frequency));
+                metadata.write(&sigmf::meta_path(&name))?;
+                (sigmf::data_path(&name), Some(metadata))
+            }
+        };
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let (sender, receiver) = channel::<(u32, Vec<u8>)>();
+
+        *recording = Some(Recording {
+            sender,
+            started: Instant::now(),
+            writer_thread: thread::spawn(move || -> io::Result<()> {
+                let mut frequency = center_frequency;
+                let mut samples: u64 = 0;
+
+                for (center_frequency, buf) in receiver {
+                    if center_frequency != frequency {
+                        frequency = center_frequency;
+                        if let Some(metadata) = metadata.as_mut() {
+                            metadata
+                                .captures
+                                .push(Capture::new(samples, frequency));
+                        }
+                    }
+                    writer.write_all(&buf)?;
+                    samples += buf.len() as u64 / 2;
+                }
+                writer.flush()?;
+                if let Some(metadata) = metadata {
+                    metadata.write(&sigmf::meta_path(&name))?;
+                }
+
+                Ok(())
+            }),
+        });
+
+        Ok(())
+    }
+
+    /// Stops the current recording and waits until all buffers are written.
+    pub fn stop(&self) -> io::Result<()> {
+        let recording = self.recording.lock().unwrap().take();
+        match recording {
+            Some(recording) => recording.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes the error that ended the last recording, if any.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error.lock().unwrap().take()
+    }
+
+    pub fn toggle(
+        &self,
+        center_frequency: u32,
+        sample_rate: u32,
+    ) -> io::Result<()> {
+        if self.elapsed().is_some() {
+            self.stop()
+        } else {
+            self.start(center_frequency, sample_rate)
+        }
+    }
+}
+
+impl Recording {
+    /// Closes the channel and waits for the writer thread.
+    fn finish(self) -> io::Result<()> {
+        drop(self.sender);
+        self.writer_thread.join().unwrap_or_else(|_| {
+            Err(io::Error::other(
+                "The writer thread of the recording panicked.",
+            ))
+        })
+    }
+}
+
+impl IqSink for Recorder {
+    fn write(&self, buf: &[u8], center_frequency: u32) {
+        let mut recording = self.recording.lock().unwrap();
+        let closed = match recording.as_ref() {
+            Some(recording) => recording
+                .sender
+                .send((center_frequency, buf.to_vec()))
+                .is_err(),
+            None => false,
+        };
+        // The writer thread only ends early after an error.
+        if closed {
+            if let Some(Err(error)) = recording.take().map(Recording::finish) {
+                eprintln!("Could not write the recording: {error}");
+                *self.error.lock().unwrap() = Some(error);
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use crate::source::rtl_tcp::{
+    MAGIC, SET_FREQUENCY, SET_GAIN, SET_GAIN_BY_INDEX, SET_SAMPLE_RATE,
+};
+use crate::source::{self, IqSink, Tuner};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The number of buffers queued for a client before further buffers are
+/// dropped for it.
+const CLIENT_QUEUE_LENGTH: usize = 256;
+
+/// Shares the IQ stream with clients speaking the `rtl_tcp` protocol.
+///
+/// Tuning commands of the clients are written to the same shared settings as
+/// the controls of the user interface, so the control thread applies them to
+/// the source.
+pub struct RtlTcpServer {
+    clients: Mutex<Vec<SyncSender<Arc<Vec<u8>>>>>,
+}
+
+struct Settings {
+    center_frequency: Arc<AtomicU32>,
+    gain: Arc<AtomicI32>,
+    gains: Vec<i32>,
+    sample_rate: Arc<AtomicU32>,
+    tuner: Tuner,
+}
+
+impl RtlTcpServer {
+    /// Starts accepting clients in a background thread.
+    pub fn start<A: ToSocketAddrs>(
+        address: A,
+        tuner: Tuner,
+        gains: Vec<i32>,
+        center_frequency: Arc<AtomicU32>,
+        gain: Arc<AtomicI32>,
+        sample_rate: Arc<AtomicU32>,
+    ) -> io::Result<Arc<RtlTcpServer>> {
+        let listener = TcpListener::bind(address)?;
+        let server = Arc::new(RtlTcpServer {
+            clients: Mutex::new(vec![]),
+        });
+        let settings = Arc::new(Settings {
+            center_frequency,
+            gain,
+            gains,
+            sample_rate,
+            tuner,
+        });
+
+        let accepting_server = server.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = accepting_server.accept(stream, settings.clone());
+            }
+        });
+
+        Ok(server)
+    }
+
+    fn accept(
+        &self,
+        mut stream: TcpStream,
+        settings: Arc<Settings>,
+    ) -> io::Result<()> {
+        let mut header = [0u8; 12];
+        header[0..4].copy_from_slice(MAGIC);
+        header[4..8].copy_from_slice(&settings.tuner.id().to_be_bytes());
+        header[8..12]
+            .copy_from_slice(&(settings.gains.len() as u32).to_be_bytes());
+        stream.write_all(&header)?;
+
+        let commands = stream.try_clone()?;
+        let (sender, receiver) = sync_channel(CLIENT_QUEUE_LENGTH);
+        self.clients.lock().unwrap().push(sender);
+        thread::spawn(move || send_samples(stream, receiver));
+        thread::spawn(move || receive_commands(commands, settings));
+
+        Ok(())
+    }
+}
+
+impl IqSink for RtlTcpServer {
+    fn write(&self, buf: &[u8], _center_frequency: u32) {
+        let mut clients = self.clients.lock().unwrap();
+        if clients.is_empty() {
+            return;
+        }
+
+        let buf = Arc::new(buf.to_vec());
+        // Slow clients miss buffers instead of stalling the waterfall.
+        clients.retain(|client| match client.try_send(buf.clone()) {
+            Ok(..) | Err(TrySendError::Full(..)) => true,
+            Err(TrySendError::Disconnected(..)) => false,
+        });
+    }
+}
+
+fn receive_commands(mut stream: TcpStream, settings: Arc<Settings>) {
+    let mut command = [0u8; 5];
+
+    while stream.read_exact(&mut command).is_ok() {
+        let parameter = u32::from_be_bytes(command[1..5].try_into().unwrap());
+        match command[0] {
+            SET_FREQUENCY => settings
+                .center_frequency
+                .store(parameter, Ordering::Relaxed),
+            // Sample rates the RTL2832U does not support are ignored.
+            SET_SAMPLE_RATE if source::is_valid_sample_rate(parameter) => {
+                settings.sample_rate.store(parameter, Ordering::Relaxed)
+            }
+            SET_GAIN => {
+                // Snap to a gain of the tuner, so that the gain controls of
+                // the user interface keep working.
+                let gain = settings
+                    .gains
+                    .iter()
+                    .min_by_key(|gain| (**gain - parameter as i32).abs());
+                if let Some(gain) = gain {
+                    settings.gain.store(*gain, Ordering::Relaxed);
+                }
+            }
+            SET_GAIN_BY_INDEX => {
+                if let Some(gain) = settings.gains.get(parameter as usize) {
+                    settings.gain.store(*gain, Ordering::Relaxed);
+                }
+            }
+            // The remaining commands, like the gain mode or the frequency
+            // correction, are not supported and therefore ignored.
+            _ => {}
+        }
+    }
+}
+
+fn send_samples(mut stream: TcpStream, receiver: Receiver<Arc<Vec<u8>>>) {
+    for buf in receiver {
+        if stream.write_all(&buf).is_err() {
+            break;
+        }
+    }
+}
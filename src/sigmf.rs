@@ -0,0 +1,145 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+//! The subset of the [SigMF](https://sigmf.org) metadata format used for
+//! recordings of unsigned 8-bit IQ samples.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DATATYPE: &str = "cu8";
+const VERSION: &str = "1.0.0";
+
+#[derive(Deserialize, Serialize)]
+pub struct Metadata {
+    pub global: Global,
+    pub captures: Vec<Capture>,
+    #[serde(default)]
+    pub annotations: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Global {
+    #[serde(rename = "core:datatype")]
+    pub datatype: String,
+    #[serde(rename = "core:hw", skip_serializing_if = "Option::is_none")]
+    pub hw: Option<String>,
+    #[serde(rename = "core:recorder", skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<String>,
+    #[serde(
+        rename = "core:sample_rate",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sample_rate: Option<f64>,
+    #[serde(rename = "core:version")]
+    pub version: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Capture {
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    #[serde(rename = "core:datetime", skip_serializing_if = "Option::is_none")]
+    pub datetime: Option<String>,
+    #[serde(
+        rename = "core:frequency",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub frequency: Option<f64>,
+}
+
+impl Metadata {
+    pub fn new(sample_rate: u32, hw: String) -> Metadata {
+        Metadata {
+            global: Global {
+                datatype: DATATYPE.to_string(),
+                hw: Some(hw),
+                recorder: Some(env!("CARGO_PKG_NAME").to_string()),
+                sample_rate: Some(sample_rate as f64),
+                version: VERSION.to_string(),
+            },
+            captures: vec![],
+            annotations: vec![],
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Metadata> {
+        let file = File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .map_err(io::Error::from)
+    }
+}
+
+impl Capture {
+    pub fn new(sample_start: u64, frequency: u32) -> Capture {
+        Capture {
+            sample_start,
+            datetime: Some(datetime(SystemTime::now())),
+            frequency: Some(frequency as f64),
+        }
+    }
+}
+
+/// Whether the path points to either file of a SigMF recording.
+pub fn is_sigmf(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension == "sigmf-data" || extension == "sigmf-meta"
+    })
+}
+
+pub fn data_path(path: &Path) -> PathBuf {
+    path.with_extension("sigmf-data")
+}
+
+pub fn meta_path(path: &Path) -> PathBuf {
+    path.with_extension("sigmf-meta")
+}
+
+/// Formats the time as ISO 8601 in UTC, for example
+/// `2025-04-01T12:34:56.789Z`.
+fn datetime(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap();
+    let seconds = since_epoch.as_secs();
+    let (hour, minute, second) =
+        (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+
+    // Convert the days since the epoch to a date in the proleptic Gregorian
+    // calendar, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (seconds / 86400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.{:03}Z",
+        since_epoch.subsec_millis()
+    )
+}
@@ -0,0 +1,179 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use std::io;
+use std::ops::RangeInclusive;
+
+pub mod file;
+pub mod rtl_tcp;
+pub mod rtlsdr;
+
+/// Common sample rates supported by the RTL2832U.
+pub const SAMPLE_RATES: [u32; 11] = [
+    250_000, 1_024_000, 1_536_000, 1_792_000, 1_920_000, 2_048_000, 2_160_000,
+    2_400_000, 2_560_000, 2_880_000, 3_200_000,
+];
+
+/// Whether the RTL2832U supports the sample rate.
+pub fn is_valid_sample_rate(sample_rate: u32) -> bool {
+    (225_001..=300_000).contains(&sample_rate)
+        || (900_001..=3_200_000).contains(&sample_rate)
+}
+
+/// Receives the raw IQ buffers of the source in the reader thread.
+pub trait IqSink: Send + Sync {
+    fn write(&self, buf: &[u8], center_frequency: u32);
+}
+
+/// The tuner chips found on RTL-SDR dongles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tuner {
+    Unknown,
+    E4000,
+    Fc0012,
+    Fc0013,
+    Fc2580,
+    R820t,
+    R828d,
+}
+
+/// A source of unsigned 8-bit interleaved IQ samples.
+///
+/// The source is shared between the control thread, which tunes it, and the
+/// reader thread, which streams the samples. Therefore all methods take
+/// `&self` and implementations must synchronize internally.
+pub trait IqSource: Send + Sync {
+    /// The available tuner gains in tenths of a dB.
+    fn available_gains(&self) -> Vec<i32>;
+
+    /// Makes a running `read_async` call return as soon as possible.
+    ///
+    /// Sources that read in a loop of their own keep a cancellation that
+    /// arrives while no `read_async` call is running, so that the next call
+    /// returns right away.
+    /// The RTL-SDR passes the cancellation to librtlsdr, which only stops a
+    /// running read.
+    fn cancel_async_read(&self);
+
+    fn center_frequency(&self) -> u32;
+
+    fn gain(&self) -> i32;
+
+    /// A description of the hardware for the metadata of recordings.
+    fn hardware(&self) -> String;
+
+    /// Blocks and passes buffers of `buf_len` bytes to the callback until
+    /// `cancel_async_read` is called.
+    ///
+    /// Every buffer comes with the center frequency and the sample rate it
+    /// was received with, as the source may be retuned while reading.
+    fn read_async(
+        &self,
+        buf_len: usize,
+        callback: &mut dyn FnMut(&[u8], u32, u32),
+    ) -> io::Result<()>;
+
+    fn sample_rate(&self) -> u32;
+
+    fn set_center_frequency(&self, frequency: u32) -> io::Result<()>;
+
+    fn set_gain(&self, gain: i32) -> io::Result<()>;
+
+    fn set_sample_rate(&self, sample_rate: u32) -> io::Result<()>;
+
+    fn tuner(&self) -> Tuner;
+}
+
+impl Tuner {
+    /// The tuner type as used by `librtlsdr` and the `rtl_tcp` protocol.
+    pub fn from_id(id: u32) -> Tuner {
+        match id {
+            1 => Tuner::E4000,
+            2 => Tuner::Fc0012,
+            3 => Tuner::Fc0013,
+            4 => Tuner::Fc2580,
+            5 => Tuner::R820t,
+            6 => Tuner::R828d,
+            _ => Tuner::Unknown,
+        }
+    }
+
+    /// Identifies the tuner by its gains, as `librtlsdr` has a distinct list
+    /// of gains for every tuner.
+    pub fn from_gains(gains: &[i32]) -> Tuner {
+        [
+            Tuner::E4000,
+            Tuner::Fc0012,
+            Tuner::Fc0013,
+            Tuner::Fc2580,
+            Tuner::R820t,
+        ]
+        .into_iter()
+        .find(|tuner| tuner.gains() == gains)
+        .unwrap_or(Tuner::Unknown)
+    }
+
+    /// The tuner type as used by `librtlsdr` and the `rtl_tcp` protocol.
+    pub fn id(&self) -> u32 {
+        match self {
+            Tuner::Unknown => 0,
+            Tuner::E4000 => 1,
+            Tuner::Fc0012 => 2,
+            Tuner::Fc0013 => 3,
+            Tuner::Fc2580 => 4,
+            Tuner::R820t => 5,
+            Tuner::R828d => 6,
+        }
+    }
+
+    /// The gains in tenths of a dB supported by the tuner as listed in
+    /// `librtlsdr`.
+    pub fn gains(&self) -> Vec<i32> {
+        match self {
+            Tuner::Unknown => vec![0],
+            Tuner::E4000 => vec![
+                -10, 15, 40, 65, 90, 115, 140, 165, 190, 215, 240, 290, 340,
+                420,
+            ],
+            Tuner::Fc0012 => vec![-99, -40, 71, 179, 192],
+            Tuner::Fc0013 => vec![
+                -99, -73, -65, -63, -60, -58, -54, 58, 61, 63, 65, 67, 68, 70,
+                71, 179, 181, 182, 184, 186, 188, 191, 197,
+            ],
+            Tuner::Fc2580 => vec![0],
+            Tuner::R820t | Tuner::R828d => vec![
+                0, 9, 14, 27, 37, 77, 87, 125, 144, 157, 166, 197, 207, 229,
+                254, 280, 297, 328, 338, 364, 372, 386, 402, 421, 434, 439,
+                445, 480, 496,
+            ],
+        }
+    }
+
+    /// The approximate range of frequencies in Hz the tuner can receive, or
+    /// `None` if the tuner is unknown.
+    pub fn frequency_range(&self) -> Option<RangeInclusive<u32>> {
+        match self {
+            Tuner::Unknown => None,
+            Tuner::E4000 => Some(52_000_000..=2_200_000_000),
+            Tuner::Fc0012 => Some(22_000_000..=948_600_000),
+            Tuner::Fc0013 => Some(22_000_000..=1_100_000_000),
+            Tuner::Fc2580 => Some(146_000_000..=924_000_000),
+            Tuner::R820t | Tuner::R828d => Some(24_000_000..=1_766_000_000),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use super::{IqSource, Tuner};
+use crate::sigmf::{self, Metadata};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_SAMPLE_RATE: u32 = 2_400_000;
+
+/// Replays a recording of unsigned 8-bit interleaved IQ samples.
+///
+/// Both the raw format written by `rtl_sdr` and SigMF recordings with the
+/// datatype `cu8` are supported.
+pub struct FileSource {
+    cancel: AtomicBool,
+    /// The sample index at which each center frequency starts.
+    captures: Vec<(u64, u32)>,
+    center_frequency: AtomicU32,
+    hardware: String,
+    playback: Mutex<Playback>,
+    pacing: bool,
+    sample_rate: u32,
+}
+
+struct Playback {
+    file: BufReader<File>,
+    sample: u64,
+}
+
+/// The contents of the sidecar file next to a raw recording.
+///
+/// The sidecar shares the name of the recording with the extension `.info`
+/// and contains lines of the form `key=value`, for example
+///
+/// ```text
+/// center_frequency=100000000
+/// sample_rate=2400000
+/// ```
+#[derive(Default)]
+pub struct Sidecar {
+    pub center_frequency: Option<u32>,
+    pub sample_rate: Option<u32>,
+}
+
+impl FileSource {
+    /// Opens the recording at `path`.
+    ///
+    /// Values that are not given as argument are taken from the SigMF
+    /// metadata or the sidecar file.
+    /// With `pacing` the samples are delivered at the recorded sample rate,
+    /// otherwise as fast as possible.
+    pub fn open(
+        path: &Path,
+        center_frequency: Option<u32>,
+        sample_rate: Option<u32>,
+        pacing: bool,
+    ) -> io::Result<FileSource> {
+        let (file, mut captures, recorded_rate, hardware) =
+            if sigmf::is_sigmf(path) {
+                let metadata = Metadata::read(&sigmf::meta_path(path))?;
+                if metadata.global.datatype != sigmf::DATATYPE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Unsupported SigMF datatype {}.",
+                            metadata.global.datatype
+                        ),
+                    ));
+                }
+                let captures = metadata
+                    .captures
+                    .iter()
+                    .filter_map(|capture| {
+                        capture.frequency.map(|frequency| {
+                            (capture.sample_start, frequency as u32)
+                        })
+                    })
+                    .collect::<Vec<(u64, u32)>>();
+                (
+                    File::open(sigmf::data_path(path))?,
+                    captures,
+                    metadata.global.sample_rate.map(|rate| rate.round() as u32),
+                    metadata.global.hw,
+                )
+            } else {
+                let sidecar = Sidecar::read(&Sidecar::path(path))?;
+                (
+                    File::open(path)?,
+                    sidecar
+                        .center_frequency
+                        .map(|frequency| vec![(0, frequency)])
+                        .unwrap_or_default(),
+                    sidecar.sample_rate,
+                    None,
+                )
+            };
+        if let Some(frequency) = center_frequency {
+            captures = vec![(0, frequency)];
+        }
+        // Recordings may come from other devices, so any rate is accepted.
+        let sample_rate =
+            sample_rate.or(recorded_rate).unwrap_or(DEFAULT_SAMPLE_RATE);
+        if sample_rate == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The sample rate of the recording is zero.",
+            ));
+        }
+        if captures.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The center frequency of the recording is unknown.",
+            ));
+        }
+
+        Ok(FileSource {
+            cancel: AtomicBool::new(false),
+            center_frequency: AtomicU32::new(captures[0].1),
+            captures,
+            hardware: hardware.unwrap_or_else(|| "unknown".to_string()),
+            playback: Mutex::new(Playback {
+                file: BufReader::new(file),
+                sample: 0,
+            }),
+            pacing,
+            sample_rate,
+        })
+    }
+
+    fn frequency_at(&self, sample: u64) -> u32 {
+        self.captures
+            .iter()
+            .take_while(|(start, _)| *start <= sample)
+            .last()
+            .unwrap_or(&self.captures[0])
+            .1
+    }
+}
+
+impl IqSource for FileSource {
+    fn available_gains(&self) -> Vec<i32> {
+        vec![0]
+    }
+
+    fn cancel_async_read(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    fn center_frequency(&self) -> u32 {
+        self.center_frequency.load(Ordering::Relaxed)
+    }
+
+    fn gain(&self) -> i32 {
+        0
+    }
+
+    fn hardware(&self) -> String {
+        self.hardware.clone()
+    }
+
+    /// Returns early when the playback reaches the next capture segment, so
+    /// that the reader picks up the new center frequency.
+    fn read_async(
+        &self,
+        buf_len: usize,
+        callback: &mut dyn FnMut(&[u8], u32, u32),
+    ) -> io::Result<()> {
+        let mut playback = self.playback.lock().unwrap();
+        let mut buf = vec![0u8; buf_len];
+        let bytes_per_second = 2. * self.sample_rate as f64;
+        let start = Instant::now();
+        let mut bytes_read: usize = 0;
+
+        while !self.cancel.swap(false, Ordering::Relaxed) {
+            playback.file.read_exact(&mut buf)?;
+            callback(
+                &buf,
+                self.frequency_at(playback.sample),
+                self.sample_rate,
+            );
+            bytes_read += buf_len;
+            playback.sample += buf_len as u64 / 2;
+
+            if self.pacing {
+                let due = Duration::from_secs_f64(
+                    bytes_read as f64 / bytes_per_second,
+                );
+                if let Some(delay) = due.checked_sub(start.elapsed()) {
+                    thread::sleep(delay);
+                }
+            }
+            let frequency = self.frequency_at(playback.sample);
+            if frequency != self.center_frequency() {
+                self.center_frequency.store(frequency, Ordering::Relaxed);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn set_center_frequency(&self, frequency: u32) -> io::Result<()> {
+        fixed_setting(frequency == self.center_frequency())
+    }
+
+    fn set_gain(&self, gain: i32) -> io::Result<()> {
+        fixed_setting(gain == 0)
+    }
+
+    fn set_sample_rate(&self, sample_rate: u32) -> io::Result<()> {
+        fixed_setting(sample_rate == self.sample_rate)
+    }
+
+    fn tuner(&self) -> Tuner {
+        Tuner::Unknown
+    }
+}
+
+impl Sidecar {
+    pub fn path(recording: &Path) -> PathBuf {
+        recording.with_extension("info")
+    }
+
+    /// Reads the sidecar file, which may be missing.
+    pub fn read(path: &Path) -> io::Result<Sidecar> {
+        let mut sidecar = Sidecar::default();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(sidecar)
+            }
+            Err(e) => return Err(e),
+        };
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            // Other tools may add further keys, which are ignored.
+            let setting = match key.trim() {
+                "center_frequency" => &mut sidecar.center_frequency,
+                "sample_rate" => &mut sidecar.sample_rate,
+                _ => continue,
+            };
+            // Frequencies may also be written as floats like `1.0e8`.
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| (0. ..=u32::MAX as f64).contains(value))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid value for {key} in the sidecar."),
+                    )
+                })?;
+            *setting = Some(value.round() as u32);
+        }
+
+        Ok(sidecar)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        if let Some(center_frequency) = self.center_frequency {
+            writeln!(file, "center_frequency={center_frequency}")?;
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            writeln!(file, "sample_rate={sample_rate}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A recording can not be retuned, so only its own settings are accepted.
+fn fixed_setting(unchanged: bool) -> io::Result<()> {
+    if unchanged {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The settings of a recording can not be changed.",
+        ))
+    }
+}
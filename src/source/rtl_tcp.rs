@@ -0,0 +1,205 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use super::{IqSource, Tuner};
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+/// The magic bytes at the start of the dongle info header.
+pub const MAGIC: &[u8; 4] = b"RTL0";
+
+pub const SET_FREQUENCY: u8 = 0x01;
+pub const SET_SAMPLE_RATE: u8 = 0x02;
+pub const SET_GAIN_MODE: u8 = 0x03;
+pub const SET_GAIN: u8 = 0x04;
+pub const SET_GAIN_BY_INDEX: u8 = 0x0d;
+
+/// An RTL-SDR shared over the network by `rtl_tcp`.
+///
+/// The protocol has no way to query the settings of the dongle, so the
+/// source remembers the values it has sent to the server.
+pub struct RtlTcpSource {
+    cancel: AtomicBool,
+    center_frequency: AtomicU32,
+    commands: Mutex<TcpStream>,
+    gain: AtomicI32,
+    sample_rate: AtomicU32,
+    samples: Mutex<BufReader<TcpStream>>,
+    tuner: Tuner,
+}
+
+impl RtlTcpSource {
+    /// Connects to the server and switches the tuner to manual gain.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<RtlTcpSource> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut samples = BufReader::new(stream.try_clone()?);
+
+        // The dongle info header consists of the magic bytes, the tuner type
+        // and the number of gains, both as big endian integers.
+        let mut header = [0u8; 12];
+        samples.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The server does not speak the rtl_tcp protocol.",
+            ));
+        }
+        let tuner = Tuner::from_id(u32::from_be_bytes(
+            header[4..8].try_into().unwrap(),
+        ));
+
+        let source = RtlTcpSource {
+            cancel: AtomicBool::new(false),
+            center_frequency: AtomicU32::new(0),
+            commands: Mutex::new(stream),
+            gain: AtomicI32::new(0),
+            sample_rate: AtomicU32::new(0),
+            samples: Mutex::new(samples),
+            tuner,
+        };
+        source.send_command(SET_GAIN_MODE, 1)?;
+
+        Ok(source)
+    }
+
+    /// Sends a command, which consists of one byte for the type and a big
+    /// endian parameter.
+    fn send_command(&self, command: u8, parameter: u32) -> io::Result<()> {
+        let mut buf = [command, 0, 0, 0, 0];
+        buf[1..5].copy_from_slice(&parameter.to_be_bytes());
+        self.commands.lock().unwrap().write_all(&buf)
+    }
+}
+
+impl IqSource for RtlTcpSource {
+    fn available_gains(&self) -> Vec<i32> {
+        self.tuner.gains()
+    }
+
+    fn cancel_async_read(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    fn center_frequency(&self) -> u32 {
+        self.center_frequency.load(Ordering::Relaxed)
+    }
+
+    fn gain(&self) -> i32 {
+        self.gain.load(Ordering::Relaxed)
+    }
+
+    fn hardware(&self) -> String {
+        format!("RTL-SDR with {:?} tuner via rtl_tcp", self.tuner)
+    }
+
+    fn read_async(
+        &self,
+        buf_len: usize,
+        callback: &mut dyn FnMut(&[u8], u32, u32),
+    ) -> io::Result<()> {
+        let mut samples = self.samples.lock().unwrap();
+        let mut buf = vec![0u8; buf_len];
+
+        while !self.cancel.swap(false, Ordering::Relaxed) {
+            samples.read_exact(&mut buf)?;
+            callback(&buf, self.center_frequency(), self.sample_rate());
+        }
+
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    fn set_center_frequency(&self, frequency: u32) -> io::Result<()> {
+        self.send_command(SET_FREQUENCY, frequency)?;
+        self.center_frequency.store(frequency, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_gain(&self, gain: i32) -> io::Result<()> {
+        self.send_command(SET_GAIN, gain as u32)?;
+        self.gain.store(gain, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_sample_rate(&self, sample_rate: u32) -> io::Result<()> {
+        self.send_command(SET_SAMPLE_RATE, sample_rate)?;
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn tuner(&self) -> Tuner {
+        self.tuner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn talks_to_a_stand_in_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut header = MAGIC.to_vec();
+            header.extend(Tuner::R820t.id().to_be_bytes());
+            header.extend(29u32.to_be_bytes());
+            stream.write_all(&header).unwrap();
+
+            let mut commands = [0u8; 20];
+            stream.read_exact(&mut commands).unwrap();
+            stream.write_all(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+            commands
+        });
+
+        let source = RtlTcpSource::connect(address).unwrap();
+        assert_eq!(source.tuner(), Tuner::R820t);
+        assert_eq!(source.available_gains(), Tuner::R820t.gains());
+        source.set_center_frequency(100_000_000).unwrap();
+        source.set_sample_rate(2_400_000).unwrap();
+        source.set_gain(496).unwrap();
+
+        let mut received = vec![];
+        source
+            .read_async(8, &mut |buf, center_frequency, sample_rate| {
+                received.push((buf.to_vec(), center_frequency, sample_rate));
+                source.cancel_async_read();
+            })
+            .unwrap();
+        assert_eq!(
+            received,
+            vec![(vec![1, 2, 3, 4, 5, 6, 7, 8], 100_000_000, 2_400_000)]
+        );
+
+        let commands = server.join().unwrap();
+        assert_eq!(commands[0..5], [SET_GAIN_MODE, 0, 0, 0, 1]);
+        assert_eq!(commands[5..10], [SET_FREQUENCY, 0x05, 0xf5, 0xe1, 0x00]);
+        assert_eq!(commands[10..15], [SET_SAMPLE_RATE, 0x00, 0x24, 0x9f, 0x00]);
+        assert_eq!(commands[15..20], [SET_GAIN, 0, 0, 0x01, 0xf0]);
+    }
+}
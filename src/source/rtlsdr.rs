@@ -0,0 +1,121 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use super::{IqSource, Tuner};
+use std::io;
+use std::sync::Mutex;
+
+/// An RTL-SDR dongle attached via USB.
+pub struct RtlSdrSource {
+    ctl: Mutex<rtlsdr_mt::Controller>,
+    reader: Mutex<rtlsdr_mt::Reader>,
+}
+
+impl RtlSdrSource {
+    pub fn open(index: u32) -> io::Result<RtlSdrSource> {
+        let (mut ctl, reader) = rtlsdr_mt::open(index).map_err(|_| {
+            io::Error::other(format!(
+                "Could not open RTL-SDR device at index {index}."
+            ))
+        })?;
+        ctl.disable_agc()
+            .map_err(|_| rtlsdr_error("disable the AGC"))?;
+
+        Ok(RtlSdrSource {
+            ctl: Mutex::new(ctl),
+            reader: Mutex::new(reader),
+        })
+    }
+}
+
+impl IqSource for RtlSdrSource {
+    fn available_gains(&self) -> Vec<i32> {
+        let mut gains = [0i32; 32];
+        self.ctl.lock().unwrap().tuner_gains(&mut gains).to_vec()
+    }
+
+    fn cancel_async_read(&self) {
+        self.ctl.lock().unwrap().cancel_async_read();
+    }
+
+    fn center_frequency(&self) -> u32 {
+        self.ctl.lock().unwrap().center_freq()
+    }
+
+    fn gain(&self) -> i32 {
+        self.ctl.lock().unwrap().tuner_gain()
+    }
+
+    fn hardware(&self) -> String {
+        "RTL-SDR".to_string()
+    }
+
+    fn read_async(
+        &self,
+        buf_len: usize,
+        callback: &mut dyn FnMut(&[u8], u32, u32),
+    ) -> io::Result<()> {
+        self.reader
+            .lock()
+            .unwrap()
+            .read_async(1, buf_len as u32, |buf| {
+                let (center_frequency, sample_rate) = {
+                    let ctl = self.ctl.lock().unwrap();
+                    (ctl.center_freq(), ctl.sample_rate())
+                };
+                callback(buf, center_frequency, sample_rate)
+            })
+            .map_err(|_| rtlsdr_error("read samples"))
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.ctl.lock().unwrap().sample_rate()
+    }
+
+    fn set_center_frequency(&self, frequency: u32) -> io::Result<()> {
+        self.ctl
+            .lock()
+            .unwrap()
+            .set_center_freq(frequency)
+            .map_err(|_| rtlsdr_error("set the center frequency"))
+    }
+
+    fn set_gain(&self, gain: i32) -> io::Result<()> {
+        self.ctl
+            .lock()
+            .unwrap()
+            .set_tuner_gain(gain)
+            .map_err(|_| rtlsdr_error("set the tuner gain"))
+    }
+
+    fn set_sample_rate(&self, sample_rate: u32) -> io::Result<()> {
+        self.ctl
+            .lock()
+            .unwrap()
+            .set_sample_rate(sample_rate)
+            .map_err(|_| rtlsdr_error("set the sample rate"))
+    }
+
+    fn tuner(&self) -> Tuner {
+        Tuner::from_gains(&self.available_gains())
+    }
+}
+
+fn rtlsdr_error(action: &str) -> io::Error {
+    io::Error::other(format!("The RTL-SDR failed to {action}."))
+}
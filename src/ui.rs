@@ -16,76 +16,223 @@
  *   along with rust-rtl-sdr-waterfall-demo. If not, see
  *   <https://www.gnu.org/licenses/>. */
 
-pub const WIDTH: u32 = 1024;
+/// The initial size of the window.
+const WIDTH: u32 = 1024;
 const HEIGHT: u32 = 800;
 const CHANNELS: u32 = 3;
-const BUF_SIZE: usize = (WIDTH * HEIGHT * CHANNELS) as usize;
-const PITCH: u32 = WIDTH * CHANNELS;
-const SPECTRUM_OFFSET: u32 = 30;
-const WATERFALL_OFFSET: u32 = 300;
-
-use crate::dsp::FftResult;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+/// The tuning steps in Hz, selected with `[` and `]`.
+const FREQUENCY_STEPS: [u32; 11] = [
+    1, 10, 100, 1_000, 5_000, 10_000, 12_500, 25_000, 100_000, 1_000_000,
+    10_000_000,
+];
+
+use crate::dsp::{CorrectionSettings, FftResult, Signal, WindowType};
+use crate::recorder::Recorder;
+use crate::source::{Tuner, SAMPLE_RATES};
+use clap::ValueEnum;
+pub use color_map::{ColorMap, ColorMapType};
+use layout::Layout;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
 use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::{Point, Rect};
 use sdl2::render::{BlendMode, Canvas, TextureQuery};
 use sdl2::rwops::RWops;
 use sdl2::ttf::{Font, FontStyle, Sdl2TtfContext};
+use sdl2::video::{FullscreenType, Window};
 use std::ops::DerefMut;
-use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
 use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use view::{Levels, View};
+use waterfall::Waterfall;
+
+mod color_map;
+mod layout;
+mod view;
+mod waterfall;
 
 const ANDIKA_BOLD_TTF: &[u8] =
     include_bytes!("../assets/Andika/Andika-Bold.ttf");
 
+/// Combines several FFT bins into a single pixel.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Decimation {
+    /// The strongest bin, which keeps narrow signals visible
+    Max,
+    /// The average power of the bins
+    Mean,
+}
+
+/// How the spectrum is drawn.
+pub struct DisplaySettings {
+    /// Let the levels follow the noise floor.
+    pub auto_range: bool,
+    pub color_map: ColorMapType,
+    /// A color map from a file, which is selected instead of `color_map`.
+    pub custom_color_map: Option<ColorMap>,
+    pub decimation: Decimation,
+    /// The span in dB below the reference level.
+    pub dynamic_range: f64,
+    /// How fast the hold traces return to the spectrum in dB per second, or
+    /// zero to hold the extremes until the traces are reset.
+    pub hold_decay: f64,
+    /// The level in dBFS at the top of the spectrum.
+    pub reference_level: f64,
+    /// The number of detected signals that are listed and marked.
+    pub signal_count: usize,
+}
+
+/// The text field for typing a frequency, opened with `F`.
+#[derive(Default)]
+struct FrequencyEntry {
+    /// Why the last submitted text was rejected.
+    error: Option<String>,
+    text: String,
+}
+
+/// Whether a hold trace follows the maxima or the minima of the spectrum.
+#[derive(Clone, Copy)]
+enum HoldKind {
+    Max,
+    Min,
+}
+
+/// A trace of the extremes of the spectrum per pixel.
+struct HoldTrace {
+    enabled: bool,
+    kind: HoldKind,
+    last_update: Option<Instant>,
+    values: Option<Vec<f64>>,
+}
+
 pub struct Ui {
+    auto_range: bool,
     canvas: Canvas<sdl2::video::Window>,
     center_frequency: Arc<AtomicU32>,
+    /// The colors of the selected color map, strongest level first.
     color_map: Vec<[u8; 3]>,
+    color_map_index: usize,
+    /// The built-in color maps followed by the custom one.
+    color_maps: Vec<ColorMap>,
+    correction_settings: CorrectionSettings,
+    /// The position of the mouse over the spectrum or the waterfall.
+    cursor: Option<(i32, i32)>,
+    display_settings: DisplaySettings,
     event_pump: sdl2::EventPump,
     fft_recv: Option<Receiver<FftResult>>,
+    frequency_entry: Option<FrequencyEntry>,
+    /// The index of the tuning step in `FREQUENCY_STEPS`.
+    frequency_step: usize,
     gain: Arc<AtomicI32>,
     gains: Option<Vec<i32>>,
-    sample_rate: u32,
+    keyboard: sdl2::keyboard::KeyboardUtil,
+    layout: Layout,
+    levels: Levels,
+    max_hold: HoldTrace,
+    min_hold: HoldTrace,
+    /// A message shown in the waterfall until the given time.
+    notice: Option<(String, Instant)>,
+    recorder: Arc<Recorder>,
+    sample_rate: Arc<AtomicU32>,
+    /// Whether the bar between the spectrum and the waterfall is dragged.
+    splitter_drag: bool,
+    /// The latest spectrum with one value per pixel.
+    spectrum: Vec<f64>,
+    text_input: sdl2::keyboard::TextInputUtil,
     texture_creator: sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+    tuner: Tuner,
     pub video_buffer: Arc<Mutex<Vec<u8>>>,
+    view: View,
+    waterfall: Waterfall,
+    window_type: Arc<Mutex<WindowType>>,
 }
 
 impl Ui {
     pub fn new(
         center_frequency: Arc<AtomicU32>,
         gain: Arc<AtomicI32>,
-        sample_rate: u32,
+        recorder: Arc<Recorder>,
+        sample_rate: Arc<AtomicU32>,
+        window_type: Arc<Mutex<WindowType>>,
+        correction_settings: CorrectionSettings,
+        mut display_settings: DisplaySettings,
     ) -> Ui {
         let sdl_context = sdl2::init().unwrap();
         let event_pump = sdl_context.event_pump().unwrap();
+        let keyboard = sdl_context.keyboard();
         let video_subsystem = sdl_context.video().unwrap();
-        let window = video_subsystem
+        let mut window = video_subsystem
             .window("Rust RTL-SDR waterfall demo", WIDTH, HEIGHT)
             .position_centered()
+            .resizable()
             .build()
             .unwrap();
+        window
+            .set_minimum_size(layout::MIN_WIDTH, layout::MIN_HEIGHT)
+            .unwrap();
         let canvas = window.into_canvas().build().unwrap();
         let texture_creator = canvas.texture_creator();
+        let mut color_maps: Vec<ColorMap> = ColorMapType::value_variants()
+            .iter()
+            .map(|color_map| color_map.color_map())
+            .collect();
+        let mut color_map_index = display_settings.color_map.index();
+        if let Some(color_map) = display_settings.custom_color_map.take() {
+            color_map_index = color_maps.len();
+            color_maps.push(color_map);
+        }
+        let layout = Layout::new(WIDTH, HEIGHT);
+        // The history covers the tallest possible waterfall, so that it is
+        // kept while the window is resized or the split is moved.
+        let history = video_subsystem
+            .desktop_display_mode(0)
+            .map_or(HEIGHT, |mode| mode.h as u32)
+            .max(HEIGHT);
+        let levels = Levels::new(
+            display_settings.reference_level,
+            display_settings.dynamic_range,
+        );
+        let text_input = video_subsystem.text_input();
+        // Text input is only needed while a frequency is typed.
+        text_input.stop();
 
         Ui {
+            auto_range: display_settings.auto_range,
             canvas: canvas,
             center_frequency: center_frequency,
-            color_map: interpolate_color_map(
-                vec![[255, 200, 20], [250, 110, 20], [60, 0, 45], [30, 20, 50]],
-                121,
-            ),
+            color_map: color_maps[color_map_index].colors(),
+            color_map_index,
+            color_maps,
+            correction_settings,
+            cursor: None,
+            display_settings,
             event_pump: event_pump,
             fft_recv: None,
+            frequency_entry: None,
+            frequency_step: 8,
             gain: gain,
             gains: None,
-            sample_rate: sample_rate,
+            keyboard,
+            layout,
+            levels,
+            max_hold: HoldTrace::new(HoldKind::Max),
+            min_hold: HoldTrace::new(HoldKind::Min),
+            notice: None,
+            recorder,
+            sample_rate,
+            splitter_drag: false,
+            spectrum: vec![],
+            text_input,
             texture_creator: texture_creator,
-            video_buffer: Arc::new(Mutex::new(vec![0u8; BUF_SIZE])),
+            tuner: Tuner::Unknown,
+            video_buffer: Arc::new(Mutex::new(vec![0u8; layout.buf_size()])),
+            view: View::new(layout.width),
+            waterfall: Waterfall::new(history as usize),
+            window_type,
         }
     }
 
@@ -101,6 +248,11 @@ impl Ui {
         self.gains = Some(g);
     }
 
+    /// Sets the tuner, whose frequency range limits typed frequencies.
+    pub fn set_tuner(&mut self, tuner: Tuner) {
+        self.tuner = tuner;
+    }
+
     pub fn set_fft_receiver(&mut self, receiver: Receiver<FftResult>) {
         self.fft_recv = Some(receiver);
     }
@@ -108,17 +260,64 @@ impl Ui {
     pub fn run(&mut self) {
         let mut current_frequency =
             self.center_frequency.load(Ordering::Relaxed);
+        let mut current_sample_rate = self.sample_rate.load(Ordering::Relaxed);
         let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string()).unwrap();
         // Font medium (16pt)
         let font_md = create_font(16, &ttf_context);
         // Font small (12pt)
         let font_sm = create_font(12, &ttf_context);
-        let mut avg: f64 = 0.;
-        let mut peak: Option<(usize, f64)> = None;
+        let mut noise_floor: f64 = 0.;
+        // The strongest signals with their horizontal position.
+        let mut signals: Vec<(i32, Signal)> = vec![];
 
         self.canvas.set_blend_mode(BlendMode::Blend);
         'running: loop {
+            let color_map_index = self.color_map_index;
+            let layout = self.layout;
+            let levels = self.levels;
+            let view = self.view;
             for event in self.event_pump.poll_iter() {
+                let step = FREQUENCY_STEPS[self.frequency_step];
+                if let Some(entry) = self.frequency_entry.as_mut() {
+                    let close = match event {
+                        Event::Quit { .. } => break 'running,
+                        Event::TextInput { text, .. } => {
+                            entry.text.push_str(&text);
+                            false
+                        }
+                        Event::KeyDown {
+                            keycode: Some(Keycode::Backspace),
+                            ..
+                        } => {
+                            entry.text.pop();
+                            false
+                        }
+                        Event::KeyDown {
+                            keycode: Some(Keycode::Escape),
+                            ..
+                        } => true,
+                        Event::KeyDown {
+                            keycode: Some(Keycode::Return | Keycode::KpEnter),
+                            ..
+                        } => match parse_frequency(&entry.text, self.tuner) {
+                            Ok(frequency) => {
+                                self.center_frequency
+                                    .store(frequency, Ordering::Relaxed);
+                                true
+                            }
+                            Err(error) => {
+                                entry.error = Some(error);
+                                false
+                            }
+                        },
+                        _ => false,
+                    };
+                    if close {
+                        self.frequency_entry = None;
+                        self.text_input.stop();
+                    }
+                    continue;
+                }
                 match event {
                     Event::Quit { .. }
                     | Event::KeyDown {
@@ -127,6 +326,221 @@ impl Ui {
                     } => {
                         break 'running;
                     }
+                    Event::MouseMotion { y, .. } if self.splitter_drag => {
+                        self.layout.move_splitter(y)
+                    }
+                    Event::MouseMotion { x, y, .. } => {
+                        self.cursor = (y
+                            >= self.layout.spectrum_offset() as i32
+                            && !self.layout.is_splitter(y))
+                        .then_some((x, y));
+                    }
+                    Event::Window {
+                        win_event: WindowEvent::Leave,
+                        ..
+                    } => self.cursor = None,
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(width, height),
+                        ..
+                    } => self.layout.resize(width as u32, height as u32),
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        y,
+                        ..
+                    } if self.layout.is_splitter(y) => {
+                        self.cursor = None;
+                        self.splitter_drag = true;
+                    }
+                    Event::MouseButtonUp {
+                        mouse_btn: MouseButton::Left,
+                        ..
+                    } => self.splitter_drag = false,
+                    Event::MouseButtonDown {
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } if y >= self.layout.spectrum_offset() as i32 => {
+                        let frequency = self.view.frequency_at(
+                            x as f64,
+                            current_frequency,
+                            current_sample_rate,
+                        );
+                        tune(&self.center_frequency, frequency, step);
+                        // Show the new center frequency in the middle.
+                        self.view.reset_pan();
+                    }
+                    Event::MouseWheel { y, mouse_x, .. } => {
+                        let keymod = self.keyboard.mod_state();
+                        if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                            self.view.zoom_by(
+                                2f64.powi(y),
+                                mouse_x as f64,
+                                current_sample_rate,
+                            );
+                        } else if keymod
+                            .intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD)
+                        {
+                            self.view
+                                .pan_by(y as f64 * 0.1, current_sample_rate);
+                        } else {
+                            change_frequency(
+                                self.center_frequency.clone(),
+                                y * step as i32,
+                                step,
+                            );
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode:
+                            Some(
+                                Keycode::Plus
+                                | Keycode::Equals
+                                | Keycode::KpPlus,
+                            ),
+                        ..
+                    } => self.view.zoom_by(
+                        2.,
+                        self.layout.width as f64 / 2.,
+                        current_sample_rate,
+                    ),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Minus | Keycode::KpMinus),
+                        ..
+                    } => self.view.zoom_by(
+                        0.5,
+                        self.layout.width as f64 / 2.,
+                        current_sample_rate,
+                    ),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Left),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                        self.view.pan_by(-0.25, current_sample_rate)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Right),
+                        keymod,
+                        ..
+                    } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                        self.view.pan_by(0.25, current_sample_rate)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::LeftBracket),
+                        ..
+                    } => {
+                        self.frequency_step =
+                            self.frequency_step.saturating_sub(1)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::RightBracket),
+                        ..
+                    } => {
+                        self.frequency_step = (self.frequency_step + 1)
+                            .min(FREQUENCY_STEPS.len() - 1)
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Home),
+                        ..
+                    } => {
+                        self.auto_range = false;
+                        self.levels.change(5., 0.);
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::End),
+                        ..
+                    } => {
+                        self.auto_range = false;
+                        self.levels.change(-5., 0.);
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Insert),
+                        ..
+                    } => self.levels.change(0., 10.),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Delete),
+                        ..
+                    } => self.levels.change(0., -10.),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F11),
+                        ..
+                    } => {
+                        if let Err(error) =
+                            toggle_fullscreen(self.canvas.window_mut())
+                        {
+                            self.notice = Some((
+                                format!("Fullscreen failed: {error}"),
+                                Instant::now() + Duration::from_secs(5),
+                            ));
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::A),
+                        ..
+                    } => self.auto_range = !self.auto_range,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::C),
+                        ..
+                    } => {
+                        self.color_map_index =
+                            (self.color_map_index + 1) % self.color_maps.len();
+                        let color_map = &self.color_maps[self.color_map_index];
+                        self.color_map = color_map.colors();
+                        self.notice = Some((
+                            format!("Colors: {}", color_map.name),
+                            Instant::now() + Duration::from_secs(2),
+                        ));
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::D),
+                        ..
+                    } => toggle(&self.correction_settings.dc_block),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F),
+                        ..
+                    } => {
+                        self.frequency_entry = Some(FrequencyEntry::default());
+                        self.text_input.start();
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::I),
+                        ..
+                    } => toggle(&self.correction_settings.iq_balance),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::M),
+                        ..
+                    } => self.max_hold.toggle(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::N),
+                        ..
+                    } => self.min_hold.toggle(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::R),
+                        ..
+                    } => {
+                        if let Err(error) = self.recorder.toggle(
+                            self.center_frequency.load(Ordering::Relaxed),
+                            self.sample_rate.load(Ordering::Relaxed),
+                        ) {
+                            self.notice = Some((
+                                format!("Recording failed: {error}"),
+                                Instant::now() + Duration::from_secs(5),
+                            ));
+                        }
+                    }
+                    Event::KeyDown {
+                        keycode: Some(Keycode::S),
+                        ..
+                    } => next_sample_rate(self.sample_rate.clone()),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::W),
+                        ..
+                    } => {
+                        let mut window_type = self.window_type.lock().unwrap();
+                        *window_type =
+                            WindowType::from_index(window_type.index() + 1);
+                    }
                     Event::KeyDown {
                         keycode: Some(Keycode::Down),
                         ..
@@ -140,20 +554,24 @@ impl Ui {
                         ..
                     } => change_frequency(
                         self.center_frequency.clone(),
-                        -100_000,
+                        -(step as i32),
+                        step,
                     ),
                     Event::KeyDown {
                         keycode: Some(Keycode::Right),
                         ..
-                    } => {
-                        change_frequency(self.center_frequency.clone(), 100_000)
-                    }
+                    } => change_frequency(
+                        self.center_frequency.clone(),
+                        step as i32,
+                        step,
+                    ),
                     Event::KeyDown {
                         keycode: Some(Keycode::PageDown),
                         ..
                     } => change_frequency(
                         self.center_frequency.clone(),
                         -2_000_000,
+                        step,
                     ),
                     Event::KeyDown {
                         keycode: Some(Keycode::PageUp),
@@ -161,41 +579,77 @@ impl Ui {
                     } => change_frequency(
                         self.center_frequency.clone(),
                         2_000_000,
+                        step,
                     ),
                     _ => {}
                 }
             }
 
-            match &self.fft_recv {
-                Some(recv) => match recv.recv() {
-                    Ok(result) => {
-                        if result.center_frequency != current_frequency {
-                            let diff = current_frequency as i32
-                                - result.center_frequency as i32;
-                            let mut raw_data =
-                                self.video_buffer.lock().unwrap();
-                            if diff.abs() > self.sample_rate as i32 {
-                                raw_data.fill(0);
-                            } else {
-                                roll(
-                                    &mut raw_data,
-                                    vec![HEIGHT, WIDTH, CHANNELS],
-                                    2,
-                                    diff.signum() * WIDTH as i32 * diff.abs()
-                                        / self.sample_rate as i32,
-                                );
-                            }
-                            current_frequency = result.center_frequency;
-                        }
-                        avg = result.avg;
-                        peak = result.peak;
-                        self.update_video_buffer(result);
-                    }
-                    Err(..) => {}
-                },
-                None => {}
+            if self.layout != layout {
+                self.apply_layout();
             }
-            self.render(&font_md, &font_sm, current_frequency, avg, peak);
+            if let Some(error) = self.recorder.take_error() {
+                self.notice = Some((
+                    format!("Recording failed: {error}"),
+                    Instant::now() + Duration::from_secs(5),
+                ));
+            }
+            // Polling keeps the events flowing at low FFT rates.
+            if let Some(Ok(result)) =
+                self.fft_recv.as_ref().map(Receiver::try_recv)
+            {
+                let retuned = result.center_frequency != current_frequency
+                    || result.sample_rate != current_sample_rate;
+                if retuned {
+                    // The held extremes belong to other frequencies.
+                    self.max_hold.reset();
+                    self.min_hold.reset();
+                    current_frequency = result.center_frequency;
+                    current_sample_rate = result.sample_rate;
+                    self.view.clamp(current_sample_rate);
+                }
+                noise_floor = result.noise_floor;
+                if self.auto_range {
+                    self.levels.follow(noise_floor);
+                }
+                signals = result
+                    .signals
+                    .iter()
+                    .take(self.display_settings.signal_count.max(1))
+                    .map(|signal| {
+                        let x = self.view.x_of(
+                            signal.frequency,
+                            current_frequency,
+                            current_sample_rate,
+                        );
+                        (x as i32, signal.clone())
+                    })
+                    .collect();
+                self.update_video_buffer(result, retuned);
+            }
+            if self.layout != layout || self.view != view {
+                // The held extremes belong to other pixels.
+                self.max_hold.reset();
+                self.min_hold.reset();
+            }
+            if self.layout != layout
+                || self.view != view
+                || self.levels != levels
+                || self.color_map_index != color_map_index
+            {
+                self.redraw_video_buffer(
+                    current_frequency,
+                    current_sample_rate,
+                );
+            }
+            self.render(
+                &font_md,
+                &font_sm,
+                current_frequency,
+                current_sample_rate,
+                noise_floor,
+                &signals,
+            );
 
             self.canvas.present();
             thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
@@ -207,85 +661,336 @@ impl Ui {
         font_md: &Font,
         font_sm: &Font,
         current_frequency: u32,
-        avg: f64,
-        peak: Option<(usize, f64)>,
+        sample_rate: u32,
+        noise_floor: f64,
+        signals: &[(i32, Signal)],
     ) {
+        let layout = self.layout;
         self.render_video_buffer();
         self.canvas.set_draw_color(Color::RGB(40, 5, 55));
-        self.canvas.fill_rect(Rect::new(0, 0, WIDTH, 30)).unwrap();
-        self.canvas.fill_rect(Rect::new(0, 270, WIDTH, 30)).unwrap();
+        self.canvas
+            .fill_rect(Rect::new(0, 0, layout.width, layout.header_height()))
+            .unwrap();
+        self.canvas
+            .fill_rect(Rect::new(
+                0,
+                layout.axis_offset() as i32,
+                layout.width,
+                layout.waterfall_offset() - layout.axis_offset(),
+            ))
+            .unwrap();
         self.canvas.set_draw_color(Color::RGBA(45, 225, 230, 50));
-        self.canvas.fill_rect(Rect::new(0, 30, 70, 240)).unwrap();
-        let freq_mhz = current_frequency as f64 / 1_000_000.;
+        self.canvas
+            .fill_rect(Rect::new(
+                0,
+                layout.spectrum_offset() as i32,
+                70,
+                layout.spectrum_height(),
+            ))
+            .unwrap();
 
-        let msamples = self.sample_rate as f64 / 1_000_000.0;
-        let pixels_per_mhz = (WIDTH as f64 / msamples) as u32;
-        let freq_sub = freq_mhz - 1.0;
-        let freq_sup = freq_mhz + 1.0;
-        self.render_text_centered(
-            &format!("{freq_sub:.3} MHz").to_string(),
-            (WIDTH / 2 - pixels_per_mhz) as i32,
-            285,
-            &font_md,
+        // Label the visible frequencies at round values.
+        let (start, span) = self.view.range(current_frequency, sample_rate);
+        let pixels_per_hz = layout.width as f64 / span;
+        let step = tick_step(250. / pixels_per_hz);
+        // Enough decimals to tell the labels apart.
+        let decimals = (6. - step.log10().floor()).clamp(3., 6.) as usize;
+        let mut tick = (start / step).ceil() * step;
+        while tick < start + span {
+            let x = (tick - start) * pixels_per_hz;
+            // Keep the labels inside the window.
+            if x >= 50. && x <= layout.width as f64 - 50. {
+                let tick_mhz = tick / 1_000_000.;
+                self.render_text_centered(
+                    &format!("{tick_mhz:.decimals$} MHz"),
+                    x as i32,
+                    layout.axis_center(),
+                    font_md,
+                );
+            }
+            tick += step;
+        }
+        let gain = self.gain.load(Ordering::Relaxed) as f64 / 10.0;
+        let rate_msps = sample_rate as f64 / 1_000_000.;
+        let window_type = *self.window_type.lock().unwrap();
+        let mut left = vec![
+            format!("Gain: {gain:.1} dB"),
+            format!("Rate: {rate_msps:.3} MS/s"),
+            format!("Window: {window_type:?}"),
+        ];
+        let corrections: Vec<&str> = [
+            (&self.correction_settings.dc_block, "DC"),
+            (&self.correction_settings.iq_balance, "IQ"),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| enabled.load(Ordering::Relaxed))
+        .map(|(_, name)| name)
+        .collect();
+        if !corrections.is_empty() {
+            left.push(format!("Correction: {}", corrections.join(" ")));
+        }
+        let mut right =
+            vec![format!("Step: {}", format_frequency(self.step()))];
+        if self.view.zoom() > 1. {
+            right.push(format!("Zoom: {}x", self.view.zoom()));
+        }
+        let auto = if self.auto_range { " (auto)" } else { "" };
+        right.push(format!(
+            "Levels: {:.0}..{:.0} dBFS{auto}",
+            self.levels.bottom(),
+            self.levels.reference(),
+        ));
+        let elapsed = self.recorder.elapsed();
+        if let Some(elapsed) = elapsed {
+            let seconds = elapsed.as_secs();
+            let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+            right.push(format!("REC {h:02}:{m:02}:{s:02}"));
+        }
+        self.render_header_texts(
+            &left,
+            false,
+            layout.header_center(0),
+            font_sm,
         );
+        // Narrow windows move the right texts to the second row.
+        let right_y = layout.header_center(layout.header_rows() - 1);
+        let lefts = self.render_header_texts(&right, true, right_y, font_sm);
+        if let (Some(_), Some(x)) = (elapsed, lefts.last()) {
+            self.canvas.set_draw_color(Color::RGB(230, 20, 40));
+            self.canvas
+                .fill_rect(Rect::new(x - 15, right_y - 5, 10, 10))
+                .unwrap();
+        }
         self.render_text_centered(
-            &format!("{freq_mhz:.3} MHz").to_string(),
-            (WIDTH / 2) as i32,
-            285,
-            &font_md,
+            &format!("Floor: {noise_floor:.1} dBFS"),
+            layout.width as i32 - 74,
+            self.spectrum_y(noise_floor),
+            font_sm,
         );
-        self.render_text_centered(
-            &format!("{freq_sup:.3} MHz").to_string(),
-            (WIDTH / 2 + pixels_per_mhz) as i32,
-            285,
-            &font_md,
+        match signals.first() {
+            Some((
+                x,
+                Signal {
+                    frequency,
+                    power: logmag,
+                    ..
+                },
+            )) if (0..layout.width as i32).contains(x) => {
+                let label = format!(
+                    "Peak: {logmag:.1} dBFS @ {:.4} MHz",
+                    frequency / 1_000_000.
+                );
+                let half_label =
+                    font_sm.size_of(&label).map_or(0, |(w, _)| w as i32 / 2);
+                // Beside the rank of the marker, flipped at the right edge.
+                let label_x = if x + 15 + 2 * half_label < layout.width as i32 {
+                    x + 15 + half_label
+                } else {
+                    x - 15 - half_label
+                };
+                let top = layout.spectrum_offset() as i32;
+                self.render_text_centered(
+                    &label,
+                    label_x,
+                    (self.spectrum_y(*logmag) - 10).max(top + 8),
+                    font_sm,
+                );
+            }
+            _ => {}
+        }
+        self.render_signals(
+            &signals[..signals.len().min(self.display_settings.signal_count)],
+            font_sm,
         );
-        let gain = self.gain.load(Ordering::Relaxed) as f64 / 10.0;
+        for tick in self.levels.ticks() {
+            self.render_text_centered(
+                &format!("{tick:.0} dBFS"),
+                35,
+                self.spectrum_y(tick),
+                font_sm,
+            );
+        }
+        if let Some((x, y)) = self.cursor {
+            self.render_cursor(x, y, current_frequency, sample_rate, font_sm);
+        }
+        self.render_frequency_entry(font_md, font_sm);
+        match &self.notice {
+            Some((text, until)) if Instant::now() < *until => {
+                let text = text.clone();
+                self.render_text_centered(
+                    &text,
+                    layout.width as i32 / 2,
+                    layout.waterfall_offset() as i32 + 30,
+                    font_md,
+                );
+            }
+            _ => self.notice = None,
+        }
+    }
+
+    /// Draws the text field for typing a frequency, if it is open.
+    fn render_frequency_entry(&mut self, font_md: &Font, font_sm: &Font) {
+        let (text, error) = match &self.frequency_entry {
+            Some(entry) => (entry.text.clone(), entry.error.clone()),
+            None => return,
+        };
+        let center_x = self.layout.width as i32 / 2;
+        self.canvas.set_draw_color(Color::RGBA(40, 5, 55, 230));
+        self.canvas
+            .fill_rect(Rect::new(center_x - 200, 110, 400, 80))
+            .unwrap();
+        self.canvas.set_draw_color(Color::RGB(45, 225, 230));
+        self.canvas
+            .draw_rect(Rect::new(center_x - 200, 110, 400, 80))
+            .unwrap();
         self.render_text_centered(
-            &format!("Gain: {gain:.1} dB").to_string(),
-            40,
-            15,
-            &font_sm,
+            &format!("Frequency: {text}_"),
+            center_x,
+            135,
+            font_md,
         );
         self.render_text_centered(
-            &format!("Avg: {avg:.1} dBFS").to_string(),
-            950,
-            SPECTRUM_OFFSET as i32 - 2 * avg as i32,
-            &font_sm,
+            &error.unwrap_or("e.g. 433.92M, 1090 MHz or 7.1e6".to_string()),
+            center_x,
+            168,
+            font_sm,
+        );
+    }
+
+    /// The current tuning step in Hz.
+    fn step(&self) -> u32 {
+        FREQUENCY_STEPS[self.frequency_step]
+    }
+
+    /// The vertical position of `logmag` in the spectrum.
+    fn spectrum_y(&self, logmag: f64) -> i32 {
+        (self.layout.spectrum_offset()
+            + self.levels.height_of(logmag, self.layout.spectrum_height()))
+            as i32
+    }
+
+    /// Reallocates the video buffer for the current size of the window.
+    /// Afterwards the video buffer must be redrawn.
+    fn apply_layout(&mut self) {
+        *self.video_buffer.lock().unwrap() = vec![0u8; self.layout.buf_size()];
+        self.view.resize(self.layout.width);
+    }
+
+    /// Draws a crosshair at the mouse with the frequency and the level of the
+    /// spectrum below it.
+    fn render_cursor(
+        &mut self,
+        x: i32,
+        y: i32,
+        center_frequency: u32,
+        sample_rate: u32,
+        font_sm: &Font,
+    ) {
+        self.canvas.set_draw_color(Color::RGBA(255, 255, 255, 120));
+        self.canvas
+            .draw_line(
+                Point::new(x, self.layout.spectrum_offset() as i32),
+                Point::new(x, self.layout.height as i32),
+            )
+            .unwrap();
+        self.canvas
+            .draw_line(
+                Point::new(0, y),
+                Point::new(self.layout.width as i32, y),
+            )
+            .unwrap();
+
+        let freq_mhz =
+            self.view
+                .frequency_at(x as f64, center_frequency, sample_rate)
+                / 1_000_000.;
+        let mut label = format!("{freq_mhz:.4} MHz");
+        if let Some(logmag) = self.spectrum.get(x as usize) {
+            label += &format!(" {logmag:.1} dBFS");
+        }
+        // Keep the label inside the window.
+        let label_x = x.clamp(100, self.layout.width as i32 - 100);
+        self.render_text_centered(&label, label_x, y - 12, font_sm);
+    }
+
+    /// Marks the signals in the spectrum with their rank and lists them in
+    /// the upper right corner.
+    fn render_signals(&mut self, signals: &[(i32, Signal)], font_sm: &Font) {
+        if signals.is_empty() {
+            return;
+        }
+        let (width, top) = (
+            self.layout.width as i32,
+            self.layout.spectrum_offset() as i32,
         );
-        self.canvas.set_draw_color(Color::RGB(255, 110, 20));
-        match peak {
-            Some((x, logmag)) => {
-                let half_width = WIDTH as f64 * 0.5;
-                let label_x = half_width + (x as f64 - half_width) * 0.8;
-                self.canvas
-                    .draw_line(
-                        Point::new(label_x as i32, 30),
-                        Point::new(
-                            x as i32,
-                            SPECTRUM_OFFSET as i32 + (logmag * -2.0) as i32,
-                        ),
-                    )
-                    .unwrap();
+        self.canvas.set_draw_color(Color::RGBA(40, 5, 55, 200));
+        self.canvas
+            .fill_rect(Rect::new(
+                width - 300,
+                top + 5,
+                295,
+                signals.len() as u32 * 16 + 8,
+            ))
+            .unwrap();
+        for (i, (x, signal)) in signals.iter().enumerate() {
+            // Only signals inside the view are marked.
+            if (0..width).contains(x) {
+                let y = self.spectrum_y(signal.power);
                 self.render_text_centered(
-                    &format!("Peak: {logmag:.1} dBFS").to_string(),
-                    label_x as i32,
-                    15,
-                    &font_sm,
+                    &format!("{}", i + 1),
+                    *x,
+                    (y - 10).max(top + 8),
+                    font_sm,
                 );
             }
-            None => {}
-        }
-        for i in (20..101).step_by(20) {
+            let freq_mhz = signal.frequency / 1_000_000.;
+            let (bw_3db, bw_6db) =
+                (signal.bandwidth_3db / 1_000., signal.bandwidth_6db / 1_000.);
             self.render_text_centered(
-                &format!("-{i} dBFS").to_string(),
-                35,
-                SPECTRUM_OFFSET as i32 + 2 * i,
-                &font_sm,
+                &format!(
+                    "{}: {freq_mhz:.4} MHz {:.1} dBFS \
+                     {bw_3db:.1}/{bw_6db:.1} kHz",
+                    i + 1,
+                    signal.power,
+                ),
+                width - 152,
+                top + 17 + i as i32 * 16,
+                font_sm,
             );
         }
     }
 
+    /// Renders the texts side by side, starting at the left or the right
+    /// edge of the window, and returns the left edge of each text.
+    fn render_header_texts(
+        &mut self,
+        texts: &[String],
+        from_right: bool,
+        y: i32,
+        font: &Font,
+    ) -> Vec<i32> {
+        const MARGIN: i32 = 10;
+        const GAP: i32 = 25;
+        let widths: Vec<i32> = texts
+            .iter()
+            .map(|text| font.size_of(text).map_or(0, |(w, _)| w as i32))
+            .collect();
+        let total =
+            widths.iter().sum::<i32>() + GAP * (texts.len() as i32 - 1).max(0);
+        let mut x = if from_right {
+            self.layout.width as i32 - MARGIN - total
+        } else {
+            MARGIN
+        };
+        let mut lefts = Vec::with_capacity(texts.len());
+        for (text, width) in texts.iter().zip(widths) {
+            self.render_text_centered(text, x + width / 2, y, font);
+            lefts.push(x);
+            x += width + GAP;
+        }
+        lefts
+    }
+
     fn render_text_centered(
         &mut self,
         text: &str,
@@ -312,12 +1017,13 @@ impl Ui {
     }
 
     fn render_video_buffer(&mut self) {
+        let Layout { height, width, .. } = self.layout;
         let mut raw_data = self.video_buffer.lock().unwrap();
         let surface = sdl2::surface::Surface::from_data(
             raw_data.deref_mut().as_mut_slice(),
-            WIDTH,
-            HEIGHT,
-            PITCH,
+            width,
+            height,
+            self.layout.pitch(),
             PixelFormatEnum::RGB24,
         )
         .unwrap();
@@ -325,65 +1031,229 @@ impl Ui {
             .texture_creator
             .create_texture_from_surface(&surface)
             .unwrap();
-        let r = Rect::new(0, 0, WIDTH, HEIGHT);
+        let r = Rect::new(0, 0, width, height);
         self.canvas.copy(&texture, None, r).unwrap();
     }
 
-    fn update_video_buffer(&self, fft_result: FftResult) {
+    /// Draws the spectrum and the hold traces for the current view.
+    fn draw_spectrum(
+        &mut self,
+        log_magnitudes: &[f64],
+        center_frequency: u32,
+        sample_rate: u32,
+    ) {
+        let log_magnitudes: Vec<f64> = view::resample(
+            log_magnitudes,
+            center_frequency,
+            sample_rate,
+            self.view.range(center_frequency, sample_rate),
+            self.view.width(),
+            self.display_settings.decimation,
+        )
+        .into_iter()
+        .map(|logmag| logmag.unwrap_or(-120.))
+        .collect();
+        let decay = self.display_settings.hold_decay;
+        self.max_hold.update(&log_magnitudes, decay);
+        self.min_hold.update(&log_magnitudes, decay);
+        self.spectrum.clone_from(&log_magnitudes);
         let mut raw_data = self.video_buffer.lock().unwrap();
-        let mut index = (WATERFALL_OFFSET * CHANNELS * WIDTH) as usize;
-        roll(
-            &mut raw_data[index..BUF_SIZE],
-            vec![HEIGHT - WATERFALL_OFFSET, WIDTH, CHANNELS],
-            1,
-            1,
-        );
+        let pitch = self.layout.pitch() as usize;
+        let index = self.layout.waterfall_offset() as usize * pitch;
         raw_data[0..index].fill(0);
 
         // Draw the horizontal lines for the amplitude spectrum
-        for i in (4..24).step_by(4) {
-            let start =
-                ((SPECTRUM_OFFSET + i * 10) * CHANNELS * WIDTH) as usize;
-            let end = start + (CHANNELS * WIDTH) as usize;
-            raw_data[start..end].fill(55);
-        }
-
-        for i in 0..WIDTH as usize {
-            // Map -120 to 0 dBFS to a value between 0 and 255
-            let l = (-1. * fft_result.log_magnitudes[i]) as usize;
-            raw_data[index..index + 3].copy_from_slice(&self.color_map[l]);
-            index += 3;
-
-            // Draw the amplitude spectrum.
-            if i % 4 == 0 {
-                let average_amplitude = fft_result.log_magnitudes[i..i + 4]
-                    .into_iter()
-                    .sum::<f64>()
-                    / 4.0
-                    * -2.0;
-                let mut offset =
-                    (((SPECTRUM_OFFSET + average_amplitude as u32) * WIDTH
-                        + i as u32)
-                        * CHANNELS) as usize;
-                for _ in 0..4 {
-                    raw_data[offset..offset + 3]
-                        .copy_from_slice(&[210, 0, 120]);
-                    offset += 3;
-                }
+        for tick in self.levels.ticks() {
+            let start = self.spectrum_y(tick) as usize * pitch;
+            raw_data[start..start + pitch].fill(55);
+        }
+
+        // Draw the amplitude spectrum.
+        for (i, pixels) in log_magnitudes.chunks(4).enumerate() {
+            let average_amplitude =
+                pixels.iter().sum::<f64>() / pixels.len() as f64;
+            let mut offset = self.spectrum_y(average_amplitude) as usize
+                * pitch
+                + i * 4 * CHANNELS as usize;
+            for _ in pixels {
+                raw_data[offset..offset + 3].copy_from_slice(&[210, 0, 120]);
+                offset += 3;
+            }
+        }
+        self.max_hold.draw(
+            &mut raw_data,
+            &self.levels,
+            &self.layout,
+            [45, 225, 230],
+        );
+        self.min_hold.draw(
+            &mut raw_data,
+            &self.levels,
+            &self.layout,
+            [120, 230, 60],
+        );
+    }
+
+    /// Draws the spectrum `age` rows before the newest one into the
+    /// waterfall.
+    fn draw_waterfall_row(
+        &self,
+        raw_data: &mut [u8],
+        age: usize,
+        center_frequency: u32,
+        sample_rate: u32,
+    ) {
+        let pitch = self.layout.pitch() as usize;
+        let start = (self.layout.waterfall_offset() as usize + age) * pitch;
+        let line = &mut raw_data[start..start + pitch];
+        let row = match self.waterfall.get(age) {
+            Some(row) => row,
+            None => return line.fill(0),
+        };
+        let pixels = view::resample(
+            &row.log_magnitudes(),
+            row.center_frequency,
+            row.sample_rate,
+            self.view.range(center_frequency, sample_rate),
+            self.view.width(),
+            self.display_settings.decimation,
+        );
+        for (pixel, logmag) in line.chunks_mut(3).zip(pixels) {
+            match logmag {
+                Some(logmag) => pixel.copy_from_slice(
+                    &self.color_map
+                        [self.levels.color_index(logmag, self.color_map.len())],
+                ),
+                None => pixel.fill(0),
             }
         }
     }
+
+    /// Draws the spectrum and the whole waterfall again, e.g. after the view
+    /// changed.
+    fn redraw_video_buffer(&mut self, center_frequency: u32, sample_rate: u32) {
+        if let Some(row) = self.waterfall.get(0) {
+            let log_magnitudes = row.log_magnitudes();
+            self.draw_spectrum(&log_magnitudes, center_frequency, sample_rate);
+        }
+        let mut raw_data = self.video_buffer.lock().unwrap();
+        for age in 0..self.layout.waterfall_height() as usize {
+            self.draw_waterfall_row(
+                &mut raw_data,
+                age,
+                center_frequency,
+                sample_rate,
+            );
+        }
+    }
+
+    /// Adds the result to the waterfall. With `redraw` all rows of the
+    /// waterfall are drawn again, otherwise they are moved down by a row.
+    fn update_video_buffer(&mut self, fft_result: FftResult, redraw: bool) {
+        let center_frequency = fft_result.center_frequency;
+        let sample_rate = fft_result.sample_rate;
+        self.waterfall.push(&fft_result);
+        if redraw {
+            return self.redraw_video_buffer(center_frequency, sample_rate);
+        }
+        self.draw_spectrum(
+            &fft_result.log_magnitudes,
+            center_frequency,
+            sample_rate,
+        );
+        let mut raw_data = self.video_buffer.lock().unwrap();
+        let Layout { width, .. } = self.layout;
+        let index =
+            (self.layout.waterfall_offset() * width * CHANNELS) as usize;
+        roll(
+            &mut raw_data[index..],
+            vec![self.layout.waterfall_height(), width, CHANNELS],
+            1,
+            1,
+        );
+        self.draw_waterfall_row(
+            &mut raw_data,
+            0,
+            center_frequency,
+            sample_rate,
+        );
+    }
 }
 
-fn change_frequency(frequency: Arc<AtomicU32>, step: i32) {
-    let mut new_frequency = frequency.load(Ordering::Relaxed);
-    if step > 0 {
-        new_frequency += step as u32;
-    } else {
-        new_frequency -= step.abs() as u32;
+impl HoldTrace {
+    fn new(kind: HoldKind) -> HoldTrace {
+        HoldTrace {
+            enabled: false,
+            kind,
+            last_update: None,
+            values: None,
+        }
+    }
+
+    /// Draws the trace into the amplitude spectrum of the video buffer.
+    fn draw(
+        &self,
+        raw_data: &mut [u8],
+        levels: &Levels,
+        layout: &Layout,
+        color: [u8; 3],
+    ) {
+        let values = match &self.values {
+            Some(values) if self.enabled => values,
+            _ => return,
+        };
+        for (x, logmag) in values.iter().enumerate() {
+            let y = layout.spectrum_offset()
+                + levels.height_of(*logmag, layout.spectrum_height());
+            let offset = ((y * layout.width + x as u32) * CHANNELS) as usize;
+            raw_data[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.last_update = None;
+        self.values = None;
+    }
+
+    fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.reset();
+    }
+
+    /// Holds the extremes of the new spectrum, while the held values decay
+    /// towards it by `decay` dB per second.
+    fn update(&mut self, log_magnitudes: &[f64], decay: f64) {
+        if !self.enabled {
+            return;
+        }
+        // Values for another number of pixels can not be compared.
+        if self
+            .values
+            .as_ref()
+            .is_some_and(|values| values.len() != log_magnitudes.len())
+        {
+            self.reset();
+        }
+        let now = Instant::now();
+        let step = self
+            .last_update
+            .map_or(0., |last| decay * (now - last).as_secs_f64());
+        self.last_update = Some(now);
+
+        let values = self.values.get_or_insert_with(|| log_magnitudes.to_vec());
+        for (value, logmag) in values.iter_mut().zip(log_magnitudes) {
+            *value = match self.kind {
+                HoldKind::Max => logmag.max(*value - step),
+                HoldKind::Min => logmag.min(*value + step),
+            };
+        }
     }
+}
 
-    frequency.store(new_frequency, Ordering::Relaxed);
+fn change_frequency(frequency: Arc<AtomicU32>, step: i32, snap: u32) {
+    let new_frequency = frequency.load(Ordering::Relaxed) as f64 + step as f64;
+
+    tune(&frequency, new_frequency, snap);
 }
 
 fn create_font<'a>(
@@ -401,27 +1271,56 @@ fn create_font<'a>(
     return font;
 }
 
-fn interpolate_color_map(
-    colors: Vec<[u8; 3]>,
-    map_size: usize,
-) -> Vec<[u8; 3]> {
-    let mut result: Vec<[u8; 3]> = vec![[0, 0, 0]; map_size];
-    // Steps between two colors
-    let s = map_size as f64 / (colors.len() - 1) as f64;
-
-    for i in 0..map_size {
-        let start = colors[(i as f64 / s).floor() as usize];
-        let end = colors[(i as f64 / s).ceil() as usize];
-        // Offset from the start to the end color from 0 to 1
-        let o = (i as f64 % s) / s;
-        result[i] = [
-            (start[0] as f64 + (end[0] as f64 - start[0] as f64) * o) as u8,
-            (start[1] as f64 + (end[1] as f64 - start[1] as f64) * o) as u8,
-            (start[2] as f64 + (end[2] as f64 - start[2] as f64) * o) as u8,
-        ];
+/// Formats a frequency with the largest unit that keeps it at least one.
+fn format_frequency(hz: u32) -> String {
+    match hz {
+        1_000_000.. => format!("{} MHz", hz as f64 / 1_000_000.),
+        1_000.. => format!("{} kHz", hz as f64 / 1_000.),
+        _ => format!("{hz} Hz"),
     }
+}
 
-    return result;
+/// Parses a frequency like `433.92M`, `1090 MHz` or `7.1e6`, where values
+/// without a unit are in Hz, and checks it against the range of the tuner.
+fn parse_frequency(text: &str, tuner: Tuner) -> Result<u32, String> {
+    let text = text.trim().to_lowercase().replace(' ', "");
+    let text = text.strip_suffix("hz").unwrap_or(&text);
+    let (number, multiplier) = match text.chars().last() {
+        Some('g') => (&text[..text.len() - 1], 1e9),
+        Some('m') => (&text[..text.len() - 1], 1e6),
+        Some('k') => (&text[..text.len() - 1], 1e3),
+        _ => (text, 1.),
+    };
+    let frequency = number
+        .parse::<f64>()
+        .map_err(|_| format!("\"{number}\" is not a number."))?
+        * multiplier;
+    if !(0. ..=u32::MAX as f64).contains(&frequency) {
+        return Err("The frequency is out of range.".to_string());
+    }
+    let frequency = frequency.round() as u32;
+    if let Some(range) = tuner.frequency_range() {
+        if !range.contains(&frequency) {
+            return Err(format!(
+                "The {tuner:?} tuner supports {} to {} MHz.",
+                range.start() / 1_000_000,
+                range.end() / 1_000_000,
+            ));
+        }
+    }
+
+    Ok(frequency)
+}
+
+/// Switches to the next of the common sample rates.
+fn next_sample_rate(sample_rate: Arc<AtomicU32>) {
+    let current_rate = sample_rate.load(Ordering::Relaxed);
+    let next_rate = SAMPLE_RATES
+        .into_iter()
+        .find(|rate| *rate > current_rate)
+        .unwrap_or(SAMPLE_RATES[0]);
+
+    sample_rate.store(next_rate, Ordering::Relaxed);
 }
 
 fn on_key_down(gain: Arc<AtomicI32>, gains: &Option<Vec<i32>>) {
@@ -464,6 +1363,39 @@ fn on_key_up(gain: Arc<AtomicI32>, gains: &Option<Vec<i32>>) {
     }
 }
 
+/// The distance in Hz between two frequency labels, which is the smallest
+/// value of the form 1, 2 or 5 times a power of ten that is at least
+/// `min_step`.
+fn tick_step(min_step: f64) -> f64 {
+    let magnitude = 10f64.powf(min_step.log10().floor());
+    [1., 2., 5., 10.]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= min_step)
+        .unwrap()
+}
+
+fn toggle(flag: &AtomicBool) {
+    flag.fetch_xor(true, Ordering::Relaxed);
+}
+
+/// Switches between the window and the whole screen.
+fn toggle_fullscreen(window: &mut Window) -> Result<(), String> {
+    let fullscreen = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    window.set_fullscreen(fullscreen)
+}
+
+/// Tunes to the multiple of `snap` Hz closest to `target`.
+fn tune(frequency: &AtomicU32, target: f64, snap: u32) {
+    let snapped = (target / snap as f64).round() * snap as f64;
+
+    frequency
+        .store(snapped.clamp(0., u32::MAX as f64) as u32, Ordering::Relaxed);
+}
+
 /// Rolls the buffer at d fields over the specified axis and fills the remaining
 /// space with zeros.
 fn roll(buf: &mut [u8], shape: Vec<u32>, axis: usize, d: i32) {
@@ -490,3 +1422,34 @@ fn roll(buf: &mut [u8], shape: Vec<u32>, axis: usize, d: i32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_frequencies_with_units() {
+        assert_eq!(parse_frequency("433.92M", Tuner::Unknown), Ok(433_920_000));
+        assert_eq!(
+            parse_frequency("1090 MHz", Tuner::Unknown),
+            Ok(1_090_000_000)
+        );
+        assert_eq!(parse_frequency("7.1e6", Tuner::Unknown), Ok(7_100_000));
+    }
+
+    #[test]
+    fn rejects_invalid_frequencies() {
+        assert_eq!(
+            parse_frequency("5G", Tuner::Unknown),
+            Err("The frequency is out of range.".to_string())
+        );
+        assert_eq!(
+            parse_frequency("abc", Tuner::Unknown),
+            Err("\"abc\" is not a number.".to_string())
+        );
+        assert_eq!(
+            parse_frequency("3000M", Tuner::R820t),
+            Err("The R820t tuner supports 24 to 1766 MHz.".to_string())
+        );
+    }
+}
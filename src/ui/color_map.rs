@@ -0,0 +1,215 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use clap::ValueEnum;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The number of colors of an interpolated color map.
+const SIZE: usize = 256;
+
+/// The built-in color maps for the waterfall.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ColorMapType {
+    /// The original purple and orange colors of this demo
+    Synthwave,
+    Viridis,
+    Inferno,
+    Turbo,
+    Grayscale,
+    /// Similar to the classic colors of Gqrx
+    Gqrx,
+    /// Similar to the classic colors of SDR#
+    Sdrsharp,
+}
+
+/// A gradient through color stops, from the weakest to the strongest level.
+pub struct ColorMap {
+    pub name: String,
+    stops: Vec<[u8; 3]>,
+}
+
+impl ColorMapType {
+    pub fn color_map(&self) -> ColorMap {
+        let stops = match self {
+            ColorMapType::Synthwave => {
+                vec![[30, 20, 50], [60, 0, 45], [250, 110, 20], [255, 200, 20]]
+            }
+            ColorMapType::Viridis => vec![
+                [68, 1, 84],
+                [72, 40, 120],
+                [62, 73, 137],
+                [49, 104, 142],
+                [38, 130, 142],
+                [31, 158, 137],
+                [53, 183, 121],
+                [110, 206, 88],
+                [181, 222, 43],
+                [253, 231, 37],
+            ],
+            ColorMapType::Inferno => vec![
+                [0, 0, 4],
+                [27, 12, 65],
+                [74, 12, 107],
+                [120, 28, 109],
+                [165, 44, 96],
+                [207, 68, 70],
+                [237, 105, 37],
+                [251, 155, 6],
+                [247, 209, 61],
+                [252, 255, 164],
+            ],
+            ColorMapType::Turbo => vec![
+                [48, 18, 59],
+                [65, 69, 171],
+                [70, 117, 237],
+                [57, 162, 252],
+                [27, 207, 212],
+                [36, 236, 166],
+                [97, 252, 108],
+                [164, 252, 59],
+                [209, 232, 52],
+                [243, 198, 58],
+                [254, 155, 45],
+                [243, 99, 21],
+                [217, 56, 6],
+                [177, 25, 1],
+                [122, 4, 3],
+            ],
+            ColorMapType::Grayscale => vec![[0, 0, 0], [255, 255, 255]],
+            ColorMapType::Gqrx => vec![
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 70],
+                [0, 0, 140],
+                [60, 125, 255],
+                [158, 190, 128],
+                [255, 255, 0],
+                [255, 191, 0],
+                [255, 128, 0],
+                [255, 64, 0],
+                [255, 0, 0],
+            ],
+            ColorMapType::Sdrsharp => vec![
+                [0, 0, 0],
+                [0, 0, 128],
+                [0, 0, 255],
+                [0, 255, 255],
+                [255, 255, 0],
+                [255, 128, 0],
+                [255, 0, 0],
+            ],
+        };
+
+        ColorMap {
+            name: format!("{self:?}"),
+            stops,
+        }
+    }
+
+    /// The position in `ColorMapType::value_variants`.
+    pub fn index(&self) -> usize {
+        ColorMapType::value_variants()
+            .iter()
+            .position(|color_map| color_map == self)
+            .unwrap()
+    }
+}
+
+impl ColorMap {
+    /// Reads the color stops from a file with a color per line, either as
+    /// `#rrggbb` or as `r, g, b` from 0 to 255. Empty lines and lines starting
+    /// with `//` are ignored.
+    pub fn load(path: &Path) -> io::Result<ColorMap> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid color stop \"{line}\"."),
+            )
+        };
+        let mut stops = vec![];
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let stop = match line.strip_prefix('#') {
+                Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16)
+                    .map(|rgb| {
+                        let [_, r, g, b] = rgb.to_be_bytes();
+                        [r, g, b]
+                    })
+                    .map_err(|_| invalid(line))?,
+                Some(..) => return Err(invalid(line)),
+                None => {
+                    let channels = line
+                        .split(',')
+                        .map(|channel| channel.trim().parse::<u8>())
+                        .collect::<Result<Vec<u8>, _>>()
+                        .map_err(|_| invalid(line))?;
+                    channels.try_into().map_err(|_| invalid(line))?
+                }
+            };
+            stops.push(stop);
+        }
+        if stops.len() < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "A color map needs at least two color stops.",
+            ));
+        }
+
+        Ok(ColorMap {
+            name: path.file_stem().map_or("Custom".to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            }),
+            stops,
+        })
+    }
+
+    /// The colors of the gradient, starting with the strongest level.
+    pub fn colors(&self) -> Vec<[u8; 3]> {
+        let mut colors = interpolate(&self.stops, SIZE);
+        colors.reverse();
+
+        colors
+    }
+}
+
+fn interpolate(colors: &[[u8; 3]], map_size: usize) -> Vec<[u8; 3]> {
+    let mut result: Vec<[u8; 3]> = vec![[0, 0, 0]; map_size];
+    // Steps between two colors
+    let s = (map_size - 1) as f64 / (colors.len() - 1) as f64;
+
+    for (i, color) in result.iter_mut().enumerate() {
+        let position = i as f64 / s;
+        let last = colors.len() - 1;
+        let start = colors[(position.floor() as usize).min(last)];
+        let end = colors[(position.ceil() as usize).min(last)];
+        // Offset from the start to the end color from 0 to 1
+        let o = position.fract();
+        *color = [
+            (start[0] as f64 + (end[0] as f64 - start[0] as f64) * o) as u8,
+            (start[1] as f64 + (end[1] as f64 - start[1] as f64) * o) as u8,
+            (start[2] as f64 + (end[2] as f64 - start[2] as f64) * o) as u8,
+        ];
+    }
+
+    result
+}
@@ -0,0 +1,133 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use super::CHANNELS;
+
+/// The height of a row of the header with the status texts.
+const HEADER_ROW_HEIGHT: u32 = 30;
+/// The height of the bar with the frequency labels between the spectrum and
+/// the waterfall, which is dragged to move the split between them.
+const AXIS_HEIGHT: u32 = 30;
+/// The smallest height of the spectrum and of the waterfall.
+const MIN_PANE_HEIGHT: u32 = 60;
+/// Narrower windows show the status texts in two rows of the header.
+const WIDE_HEADER_WIDTH: u32 = 1024;
+/// The smallest size of the window, which still fits the header.
+pub const MIN_WIDTH: u32 = 640;
+pub const MIN_HEIGHT: u32 =
+    2 * HEADER_ROW_HEIGHT + AXIS_HEIGHT + 4 * MIN_PANE_HEIGHT;
+
+/// The positions of the header, the spectrum, the axis and the waterfall in
+/// a window of the given size, from top to bottom.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub height: u32,
+    /// The height of the spectrum as a fraction of the height of the
+    /// spectrum and the waterfall together.
+    split: f64,
+    pub width: u32,
+}
+
+impl Layout {
+    pub fn new(width: u32, height: u32) -> Layout {
+        Layout {
+            height,
+            split: 240. / 740.,
+            width,
+        }
+    }
+
+    /// The center of the bar with the frequency labels.
+    pub fn axis_center(&self) -> i32 {
+        (self.axis_offset() + AXIS_HEIGHT / 2) as i32
+    }
+
+    /// The top of the bar with the frequency labels.
+    pub fn axis_offset(&self) -> u32 {
+        self.header_height() + self.spectrum_height()
+    }
+
+    /// The size in bytes of a video buffer for the whole window.
+    pub fn buf_size(&self) -> usize {
+        (self.pitch() * self.height) as usize
+    }
+
+    /// The vertical center of the given row of the header.
+    pub fn header_center(&self, row: u32) -> i32 {
+        (row * HEADER_ROW_HEIGHT + HEADER_ROW_HEIGHT / 2) as i32
+    }
+
+    pub fn header_height(&self) -> u32 {
+        self.header_rows() * HEADER_ROW_HEIGHT
+    }
+
+    pub fn header_rows(&self) -> u32 {
+        if self.width < WIDE_HEADER_WIDTH {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Whether `y` is on the bar between the spectrum and the waterfall.
+    pub fn is_splitter(&self, y: i32) -> bool {
+        (self.axis_offset() as i32..self.waterfall_offset() as i32).contains(&y)
+    }
+
+    /// Moves the bar between the spectrum and the waterfall, so that its
+    /// center is at `y`.
+    pub fn move_splitter(&mut self, y: i32) {
+        let spectrum_height =
+            y - (self.header_height() + AXIS_HEIGHT / 2) as i32;
+        self.split =
+            (spectrum_height as f64 / self.panes_height() as f64).clamp(0., 1.);
+    }
+
+    /// The number of bytes of a row of pixels.
+    pub fn pitch(&self) -> u32 {
+        self.width * CHANNELS
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width.max(MIN_WIDTH);
+        self.height = height.max(MIN_HEIGHT);
+    }
+
+    pub fn spectrum_height(&self) -> u32 {
+        let panes_height = self.panes_height();
+        ((panes_height as f64 * self.split).round() as u32)
+            .clamp(MIN_PANE_HEIGHT, panes_height - MIN_PANE_HEIGHT)
+    }
+
+    pub fn spectrum_offset(&self) -> u32 {
+        self.header_height()
+    }
+
+    pub fn waterfall_height(&self) -> u32 {
+        self.height - self.waterfall_offset()
+    }
+
+    pub fn waterfall_offset(&self) -> u32 {
+        self.axis_offset() + AXIS_HEIGHT
+    }
+
+    /// The height of the spectrum and the waterfall together.
+    fn panes_height(&self) -> u32 {
+        self.height - self.header_height() - AXIS_HEIGHT
+    }
+}
@@ -0,0 +1,246 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use super::{tick_step, Decimation};
+
+/// The largest zoom factor.
+const MAX_ZOOM: f64 = 64.;
+/// How far in dB the noise floor may move before the automatic range
+/// follows.
+const AUTO_RANGE_HYSTERESIS: f64 = 3.;
+/// The distance in dB of the bottom of the automatic range below the noise
+/// floor.
+const AUTO_RANGE_MARGIN: f64 = 10.;
+
+/// The levels that are shown in the spectrum and colored in the waterfall.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Levels {
+    /// The span in dB below the reference level.
+    range: f64,
+    /// The level in dBFS at the top of the spectrum.
+    reference: f64,
+}
+
+/// The part of the spectrum that is shown in the window.
+#[derive(Clone, Copy, PartialEq)]
+pub struct View {
+    /// The distance in Hz of the center of the view from the center
+    /// frequency.
+    pan: f64,
+    /// The width of the view in pixels.
+    width: u32,
+    /// How many times narrower the view is than the sample rate.
+    zoom: f64,
+}
+
+impl Levels {
+    pub fn new(reference: f64, range: f64) -> Levels {
+        let mut levels = Levels { range, reference };
+        levels.change(0., 0.);
+
+        levels
+    }
+
+    /// The level in dBFS at the bottom of the spectrum.
+    pub fn bottom(&self) -> f64 {
+        self.reference - self.range
+    }
+
+    /// Moves the reference level and widens the range by the given dB.
+    pub fn change(&mut self, reference: f64, range: f64) {
+        self.range = (self.range + range).clamp(10., 120.);
+        self.reference = (self.reference + reference).clamp(-100., 0.);
+    }
+
+    /// The index of the color of `logmag` in a color map of `size` colors,
+    /// which starts with the color for the reference level.
+    pub fn color_index(&self, logmag: f64, size: usize) -> usize {
+        (self.position(logmag) * (size - 1) as f64).round() as usize
+    }
+
+    /// Moves the range, so that its bottom stays just below the noise floor.
+    pub fn follow(&mut self, noise_floor: f64) {
+        let reference = noise_floor - AUTO_RANGE_MARGIN + self.range;
+        if (reference - self.reference).abs() > AUTO_RANGE_HYSTERESIS {
+            self.change(reference.round() - self.reference, 0.);
+        }
+    }
+
+    /// The distance in pixels of `logmag` from the top of a spectrum of the
+    /// given height.
+    pub fn height_of(&self, logmag: f64, height: u32) -> u32 {
+        (self.position(logmag) * height as f64) as u32
+    }
+
+    pub fn reference(&self) -> f64 {
+        self.reference
+    }
+
+    /// The round levels in dBFS between the bottom and the reference level.
+    pub fn ticks(&self) -> Vec<f64> {
+        let step = tick_step(self.range / 6.);
+        let mut ticks = vec![];
+        let mut tick = (self.bottom() / step).floor() * step + step;
+        while tick < self.reference {
+            ticks.push(tick);
+            tick += step;
+        }
+
+        ticks
+    }
+
+    /// The position of `logmag` from 0 at the reference level to 1 at the
+    /// bottom.
+    fn position(&self, logmag: f64) -> f64 {
+        ((self.reference - logmag) / self.range).clamp(0., 1.)
+    }
+}
+
+impl View {
+    pub fn new(width: u32) -> View {
+        View {
+            pan: 0.,
+            width,
+            zoom: 1.,
+        }
+    }
+
+    /// The frequency in Hz at the horizontal position `x` in the window.
+    pub fn frequency_at(
+        &self,
+        x: f64,
+        center_frequency: u32,
+        sample_rate: u32,
+    ) -> f64 {
+        let (start, span) = self.range(center_frequency, sample_rate);
+
+        start + x * span / self.width as f64
+    }
+
+    /// Moves the view by the given fraction of its span.
+    pub fn pan_by(&mut self, fraction: f64, sample_rate: u32) {
+        self.pan += fraction * sample_rate as f64 / self.zoom;
+        self.clamp(sample_rate);
+    }
+
+    /// The lowest frequency and the span of the view in Hz.
+    pub fn range(&self, center_frequency: u32, sample_rate: u32) -> (f64, f64) {
+        let span = sample_rate as f64 / self.zoom;
+
+        (center_frequency as f64 + self.pan - span / 2., span)
+    }
+
+    /// Keeps the frequencies of the view, but spreads them over `width`
+    /// pixels.
+    pub fn resize(&mut self, width: u32) {
+        self.width = width;
+    }
+
+    /// Centers the view on the center frequency.
+    pub fn reset_pan(&mut self) {
+        self.pan = 0.;
+    }
+
+    /// The horizontal position of `frequency` in the window.
+    pub fn x_of(
+        &self,
+        frequency: f64,
+        center_frequency: u32,
+        sample_rate: u32,
+    ) -> f64 {
+        let (start, span) = self.range(center_frequency, sample_rate);
+
+        (frequency - start) * self.width as f64 / span
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// Zooms by `factor`, while the frequency at the horizontal position
+    /// `anchor` stays in place.
+    pub fn zoom_by(&mut self, factor: f64, anchor: f64, sample_rate: u32) {
+        let zoom = (self.zoom * factor).clamp(1., MAX_ZOOM);
+        let anchor_offset =
+            (anchor / self.width as f64 - 0.5) * sample_rate as f64;
+        self.pan += anchor_offset / self.zoom - anchor_offset / zoom;
+        self.zoom = zoom;
+        self.clamp(sample_rate);
+    }
+
+    /// Keeps the view inside the spectrum.
+    pub fn clamp(&mut self, sample_rate: u32) {
+        let limit = (sample_rate as f64 - sample_rate as f64 / self.zoom) / 2.;
+        self.pan = self.pan.clamp(-limit, limit);
+    }
+}
+
+/// Maps the FFT bins of a spectrum to the pixels of the view given by its
+/// lowest frequency and its span, see `View::range`, and its width. Pixels
+/// outside the spectrum are `None`.
+///
+/// With more bins than pixels the bins of every pixel are combined according
+/// to the decimation, otherwise each bin is repeated over its pixels.
+pub fn resample(
+    log_magnitudes: &[f64],
+    center_frequency: u32,
+    sample_rate: u32,
+    (start, span): (f64, f64),
+    width: u32,
+    decimation: Decimation,
+) -> Vec<Option<f64>> {
+    let bins = log_magnitudes.len();
+    let hz_per_bin = sample_rate as f64 / bins as f64;
+    let first_bin =
+        (start - center_frequency as f64) / hz_per_bin + (bins / 2) as f64;
+    let bins_per_pixel = span / hz_per_bin / width as f64;
+
+    (0..width as usize)
+        .map(|x| {
+            let from = first_bin + x as f64 * bins_per_pixel;
+            let to = from + bins_per_pixel;
+            if to <= 0. || from >= bins as f64 {
+                return None;
+            }
+            if bins_per_pixel <= 1. {
+                return Some(log_magnitudes[from.max(0.) as usize]);
+            }
+
+            let from = (from.round().max(0.) as usize).min(bins - 1);
+            let to = (to.round() as usize).clamp(from + 1, bins);
+            let pixel = &log_magnitudes[from..to];
+            Some(match decimation {
+                Decimation::Max => {
+                    pixel.iter().copied().fold(f64::MIN, f64::max)
+                }
+                Decimation::Mean => {
+                    let power = pixel
+                        .iter()
+                        .map(|logmag| 10f64.powf(logmag / 10.))
+                        .sum::<f64>()
+                        / pixel.len() as f64;
+                    10. * power.log10()
+                }
+            })
+        })
+        .collect()
+}
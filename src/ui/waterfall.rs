@@ -0,0 +1,75 @@
+/* Copyright (c) 2025 by Karsten Lehmann <mail@kalehmann.de>
+ *
+ *   This file is part of rust-rtl-sdr-waterfall-demo.
+ *
+ *   rust-rtl-sdr-waterfall-demo is free software: you can redistribute it
+ *   and/or modify it under the terms of the GNU Affero General Public License
+ *   as published by the Free Software Foundation, either version 3 of the
+ *   License, or (at your option) any later version.
+ *
+ *   rust-rtl-sdr-waterfall-demo is distributed in the hope that it will be
+ *   useful, but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Affero
+ *   General Public License for more details.
+ *
+ *   You should have received a copy of the GNU Affero General Public License
+ *   along with rust-rtl-sdr-waterfall-demo. If not, see
+ *   <https://www.gnu.org/licenses/>. */
+
+use crate::dsp::FftResult;
+use std::collections::VecDeque;
+
+/// A spectrum of the waterfall.
+pub struct Row {
+    pub center_frequency: u32,
+    /// The magnitudes of the bins in steps of half a dB below 0 dBFS.
+    levels: Vec<u8>,
+    pub sample_rate: u32,
+}
+
+/// The latest spectra at the full resolution of the FFT, so that the
+/// waterfall can be drawn again whenever the view changes.
+pub struct Waterfall {
+    capacity: usize,
+    /// The rows, newest first.
+    rows: VecDeque<Row>,
+}
+
+impl Row {
+    /// The magnitudes of the bins in dBFS.
+    pub fn log_magnitudes(&self) -> Vec<f64> {
+        self.levels
+            .iter()
+            .map(|level| *level as f64 * -0.5)
+            .collect()
+    }
+}
+
+impl Waterfall {
+    pub fn new(capacity: usize) -> Waterfall {
+        Waterfall {
+            capacity,
+            rows: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The row `age` spectra before the newest one.
+    pub fn get(&self, age: usize) -> Option<&Row> {
+        self.rows.get(age)
+    }
+
+    pub fn push(&mut self, result: &FftResult) {
+        if self.rows.len() == self.capacity {
+            self.rows.pop_back();
+        }
+        self.rows.push_front(Row {
+            center_frequency: result.center_frequency,
+            levels: result
+                .log_magnitudes
+                .iter()
+                .map(|logmag| (logmag * -2.).round().clamp(0., 240.) as u8)
+                .collect(),
+            sample_rate: result.sample_rate,
+        });
+    }
+}